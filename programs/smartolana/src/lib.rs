@@ -1,4 +1,4 @@
-#![allow(unexpected_cfgs)]
+#![allow(unexpected_cfgs, deprecated)]
 /**
  * THE OVERALL FLOW
  * program logic,
@@ -6,15 +6,13 @@
  * account structs
  */
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::program_pack::Pack;
-use anchor_spl::associated_token::AssociatedToken;
-use anchor_spl::associated_token::{self, Create};
-use anchor_spl::token::spl_token::state::Mint as SplMint;
-use anchor_spl::token::{
-    self, set_authority, spl_token, Burn, InitializeMint, Mint, MintTo, SetAuthority, Token,
-    TokenAccount, Transfer,
+use anchor_lang::solana_program::program_option::COption;
+use anchor_spl::associated_token::{self, AssociatedToken, Create};
+use anchor_spl::token_2022::spl_token_2022::{self, instruction::AuthorityType};
+use anchor_spl::token_interface::{
+    self, set_authority, Approve, Burn, CloseAccount, Mint, MintTo, Revoke, SetAuthority,
+    TokenAccount, TokenInterface, TransferChecked,
 };
-use spl_token::instruction::AuthorityType;
 
 declare_id!("BH2vhWg3AJqKn5VXKf6nepTPQUigJEhPEApUo9XXekjz");
 
@@ -24,11 +22,11 @@ It signals to Anchor the account is an executable one, i.e. a program, and you m
 The one we have been using is the system program, though later we will use our own programs.
  */
 pub mod smartolana {
-    use anchor_lang::accounts;
-
     use super::*;
 
     pub fn init_profile(ctx: Context<InitProfile>, name: String) -> Result<()> {
+        UserProfile::validate_metadata(&name, "", "", "")?;
+
         let profile = &mut ctx.accounts.profile;
         profile.authority = ctx.accounts.authority.key();
         profile.name = name;
@@ -36,276 +34,505 @@ pub mod smartolana {
         Ok(())
     }
 
-    pub fn create_mint(ctx: Context<CreateMint>) -> Result<()> {
-        let mint_info = ctx.accounts.mint.to_account_info();
-        let mint_data = mint_info.try_borrow_data()?;
-        let is_initialized = SplMint::unpack(&mint_data).is_ok();
+    pub fn update_profile(
+        ctx: Context<UpdateProfile>,
+        name: String,
+        bio: String,
+        avatar_uri: String,
+        website: String,
+    ) -> Result<()> {
+        UserProfile::validate_metadata(&name, &bio, &avatar_uri, &website)?;
 
-        if !is_initialized {
-            let bump = ctx.bumps.mint_authority;
-            let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
+        // Account was already resized to fit the new metadata by the realloc constraint
+        let profile = &mut ctx.accounts.profile;
+        profile.name = name;
+        profile.bio = bio;
+        profile.avatar_uri = avatar_uri;
+        profile.website = website;
 
-            let cpi_ctx = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                InitializeMint {
-                    mint: mint_info.clone(),
-                    rent: ctx.accounts.rent.to_account_info(),
-                },
-                signer_seeds,
-            );
+        Ok(())
+    }
 
-            token::initialize_mint(
-                cpi_ctx,
-                9,
-                ctx.accounts.mint_authority.key,
-                Some(ctx.accounts.mint_authority.key),
-            )?;
-        }
+    pub fn close_profile(ctx: Context<CloseProfile>) -> Result<()> {
+        let profile = &ctx.accounts.profile;
+
+        // Validators, proposals, stakes and the handle record all point back at this PDA
+        require!(
+            profile.validator_count == 0
+                && profile.proposal_count == 0
+                && profile.active_stakes == 0
+                && profile.pending_migrations == 0
+                && profile.handle.is_empty(),
+            CustomError::ProfileInUse
+        );
 
+        msg!("Closed profile for {}", profile.authority);
         Ok(())
     }
 
-    pub fn init_validator(ctx: Context<InitValidator>, id: u64, name: String) -> Result<()> {
-        let validator = &mut ctx.accounts.validator;
-        validator.id = id;
-        validator.name = name;
-        validator.is_active = true;
-        validator.authority = ctx.accounts.authority.key();
-        validator.profile = ctx.accounts.profile.key();
-        validator.bump = ctx.bumps.validator;
+    pub fn claim_handle(ctx: Context<ClaimHandle>, handle: String) -> Result<()> {
+        let handle = normalize_handle(&handle)?;
+        let profile = &mut ctx.accounts.profile;
 
-        let bump = ctx.bumps.mint_authority;
-        let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
+        require!(profile.handle.is_empty(), CustomError::HandleAlreadySet);
 
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            MintTo {
-                mint: ctx.accounts.mint.to_account_info(),
-                to: ctx.accounts.validator_ata.to_account_info(),
-                authority: ctx.accounts.mint_authority.to_account_info(),
-            },
-            signer_seeds,
-        );
+        let record = &mut ctx.accounts.handle_record;
+        record.handle = handle.clone();
+        record.profile = profile.key();
+        record.authority = ctx.accounts.authority.key();
+        record.claimed_at = Clock::get()?.unix_timestamp;
+        record.bump = ctx.bumps.handle_record;
+
+        // Reverse lookup so clients can go profile -> @handle without scanning
+        profile.handle = handle;
 
-        token::mint_to(cpi_ctx, 100_000_000_000)?; // 100 tokens with 9 decimals
+        msg!("Profile {} claimed @{}", record.profile, record.handle);
         Ok(())
     }
 
-    pub fn update_validator(
-        ctx: Context<UpdateValidator>,
-        new_name: String,
-        is_active: bool,
-    ) -> Result<()> {
-        let validator = &mut ctx.accounts.validator;
-        validator.name = new_name;
-        validator.is_active = is_active;
+    pub fn release_handle(ctx: Context<ReleaseHandle>) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+        msg!("Profile {} released @{}", profile.key(), profile.handle);
+
+        profile.handle = String::new();
         Ok(())
     }
 
-    pub fn close_validator(_ctx: Context<CloseValidator>) -> Result<()> {
+    pub fn transfer_handle(ctx: Context<TransferHandle>) -> Result<()> {
+        let from_profile = &mut ctx.accounts.from_profile;
+        let to_profile = &mut ctx.accounts.to_profile;
+
+        require!(to_profile.handle.is_empty(), CustomError::HandleAlreadySet);
+
+        let record = &mut ctx.accounts.handle_record;
+        record.profile = to_profile.key();
+        record.authority = ctx.accounts.new_authority.key();
+        record.claimed_at = Clock::get()?.unix_timestamp;
+
+        to_profile.handle = std::mem::take(&mut from_profile.handle);
+
+        msg!(
+            "Transferred @{} from {} to {}",
+            record.handle,
+            from_profile.key(),
+            to_profile.key()
+        );
         Ok(())
     }
 
-    pub fn transfer_tokens(ctx: Context<TransferTokens>, amount: u64) -> Result<()> {
-        // Manual verification that the sender owns the 'from' ATA
+    pub fn set_guardians(
+        ctx: Context<SetGuardians>,
+        guardians: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
+
         require!(
-            ctx.accounts.from.owner == ctx.accounts.sender.key(),
-            CustomError::Unauthorized
+            guardians.len() <= UserProfile::MAX_GUARDIANS,
+            CustomError::InvalidGuardianSet
+        );
+        require!(
+            (guardians.is_empty() && threshold == 0)
+                || (threshold > 0 && threshold as usize <= guardians.len()),
+            CustomError::InvalidGuardianSet
         );
+        for (i, guardian) in guardians.iter().enumerate() {
+            require!(
+                *guardian != profile.authority && !guardians[..i].contains(guardian),
+                CustomError::InvalidGuardianSet
+            );
+        }
+
+        profile.guardians = guardians;
+        profile.guardian_threshold = threshold;
+
+        // Approvals were indexed against the old set, so any pending rotation starts over
+        profile.clear_pending_rotation();
 
-        token::transfer(ctx.accounts.into_transfer_context(), amount)?;
         Ok(())
     }
 
-    pub fn burn_tokens(ctx: Context<BurnTokens>, amount: u64) -> Result<()> {
-        let cpi_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Burn {
-                mint: ctx.accounts.mint.to_account_info(),
-                from: ctx.accounts.owner_ata.to_account_info(),
-                authority: ctx.accounts.owner.to_account_info(),
-            },
+    pub fn propose_rotation(ctx: Context<ProposeRotation>, new_authority: Pubkey) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let profile = &mut ctx.accounts.profile;
+
+        require!(
+            new_authority != profile.authority,
+            CustomError::InvalidNewAuthority
         );
 
-        token::burn(cpi_ctx, amount)?;
+        profile.pending_authority = new_authority;
+        profile.rotation_eta = now + UserProfile::ROTATION_TIMELOCK;
+        // Guardians backing another key have to reach the threshold again to override this
+        profile.recovery_authority = Pubkey::default();
+        profile.recovery_approvals = 0;
+
+        msg!(
+            "Rotation of {} to {} unlocks at {}",
+            profile.authority,
+            new_authority,
+            profile.rotation_eta
+        );
         Ok(())
     }
 
-    pub fn reassign_mint_authority(
-        ctx: Context<ReassignMintAuthority>,
-        new_authority: Pubkey,
-    ) -> Result<()> {
-        let bump = ctx.bumps.mint_authority;
-        let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
+    pub fn approve_recovery(ctx: Context<ApproveRecovery>, new_authority: Pubkey) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let profile = &mut ctx.accounts.profile;
+        let guardian = ctx.accounts.guardian.key();
 
-        set_authority(
-            CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                SetAuthority {
-                    current_authority: ctx.accounts.mint_authority.to_account_info(),
-                    account_or_mint: ctx.accounts.mint.to_account_info(),
-                },
-                signer_seeds,
-            ),
-            AuthorityType::MintTokens,
-            Some(new_authority),
-        )?;
+        let index = profile
+            .guardians
+            .iter()
+            .position(|g| g == &guardian)
+            .ok_or(CustomError::NotAGuardian)?;
+
+        require!(
+            new_authority != profile.authority,
+            CustomError::InvalidNewAuthority
+        );
 
+        let approvals = profile.approve_recovery(index, new_authority, now);
+
+        msg!(
+            "Guardian {} approved recovery to {} ({}/{})",
+            guardian,
+            new_authority,
+            approvals,
+            profile.guardian_threshold
+        );
         Ok(())
     }
 
-    pub fn create_proposal(
-        ctx: Context<CreateProposal>,
-        proposal_id: u64,
-        title: String,
-        description: String,
-        deadline: i64,
-    ) -> Result<()> {
-        let clock = Clock::get()?;
+    pub fn cancel_rotation(ctx: Context<CancelRotation>) -> Result<()> {
+        let profile = &mut ctx.accounts.profile;
         require!(
-            deadline > clock.unix_timestamp,
-            CustomError::InvalidDeadline
+            profile.pending_authority != Pubkey::default(),
+            CustomError::NoRotationPending
         );
 
-        let proposal = &mut ctx.accounts.proposal;
-        proposal.id = proposal_id;
-        proposal.profile = ctx.accounts.profile.key();
-        proposal.title = title;
-        proposal.description = description;
-        proposal.created_at = clock.unix_timestamp;
-        proposal.deadline = deadline;
-        proposal.yes_votes = 0;
-        proposal.no_votes = 0;
-        proposal.bump = ctx.bumps.proposal;
-
+        profile.clear_pending_rotation();
         Ok(())
     }
 
-    pub fn vote_on_proposal(ctx: Context<VoteOnProposal>, vote: bool) -> Result<()> {
-        let clock = Clock::get()?;
-        let proposal = &mut ctx.accounts.proposal;
+    pub fn rotate_authority(ctx: Context<RotateAuthority>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let old_profile = &ctx.accounts.old_profile;
+        let new_authority = ctx.accounts.new_authority.key();
 
         require!(
-            clock.unix_timestamp < proposal.deadline,
-            CustomError::ProposalExpired
+            old_profile.pending_authority == new_authority,
+            CustomError::NoRotationPending
+        );
+        require!(
+            old_profile.rotation_eta != 0 && now >= old_profile.rotation_eta,
+            CustomError::RotationTimelocked
+        );
+        // Migrations read `previous_authority`, which another rotation would overwrite
+        require!(
+            old_profile.pending_migrations == 0,
+            CustomError::MigrationsPending
         );
 
-        if vote {
-            proposal.yes_votes += 1;
-        } else {
-            proposal.no_votes += 1;
-        }
+        let profile = &mut ctx.accounts.profile;
+        profile.authority = new_authority;
+        profile.name = old_profile.name.clone();
+        profile.bio = old_profile.bio.clone();
+        profile.avatar_uri = old_profile.avatar_uri.clone();
+        profile.website = old_profile.website.clone();
+        profile.handle = old_profile.handle.clone();
+        profile.guardians = old_profile.guardians.clone();
+        profile.guardian_threshold = old_profile.guardian_threshold;
+        profile.previous_authority = old_profile.authority;
+        profile.previous_profile = old_profile.key();
+        profile.bump = ctx.bumps.profile;
 
-        let vote_record = &mut ctx.accounts.vote_record;
-        vote_record.proposal = proposal.key();
-        vote_record.validator = ctx.accounts.validator.key();
-        vote_record.vote = vote;
-        vote_record.timestamp = clock.unix_timestamp;
-        vote_record.bump = ctx.bumps.vote_record;
+        // Validators and stakes are counted again as they get migrated; proposals
+        // stay with the old profile PDA they were seeded from but still count here
+        // until closed through `previous_profile`, so the profile can't be closed
+        // or rotated again under them
+        profile.validator_count = 0;
+        profile.active_stakes = 0;
+        profile.proposal_count = old_profile.proposal_count;
+        profile.pending_migrations = old_profile
+            .validator_count
+            .checked_add(old_profile.active_stakes)
+            .and_then(|n| n.checked_add(old_profile.proposal_count))
+            .ok_or(CustomError::MathOverflow)?;
+
+        if !profile.handle.is_empty() {
+            let record = ctx
+                .accounts
+                .handle_record
+                .as_mut()
+                .ok_or(CustomError::HandleRecordRequired)?;
+            record.profile = profile.key();
+            record.authority = new_authority;
+        }
 
+        msg!(
+            "Rotated profile authority {} -> {}",
+            old_profile.authority,
+            new_authority
+        );
         Ok(())
     }
 
-    pub fn stake_tokens(ctx: Context<StakeTokens>, amount: u64) -> Result<()> {
-        let stake_vault = &mut ctx.accounts.stake_vault;
+    pub fn migrate_validator(ctx: Context<MigrateValidator>) -> Result<()> {
+        let old_validator = &ctx.accounts.old_validator;
 
-        require!(stake_vault.amount == 0, CustomError::AlreadyStaked);
-        require!(amount > 0, CustomError::ZeroStake);
+        let validator = &mut ctx.accounts.validator;
+        validator.id = old_validator.id;
+        validator.name = old_validator.name.clone();
+        validator.status = old_validator.status;
+        validator.jailed_until = old_validator.jailed_until;
+        validator.unbonding_until = old_validator.unbonding_until;
+        // Commission and reward accounting carry over; delegations can't exist here
+        validator.pool = old_validator.pool;
+        validator.commission_bps = old_validator.commission_bps;
+        validator.pending_commission = old_validator.pending_commission;
+        validator.reward_per_token_paid = old_validator.reward_per_token_paid;
+        validator.acc_reward_per_share = old_validator.acc_reward_per_share;
+        // Still owed on close, from the new authority's ATA
+        validator.grant_amount = old_validator.grant_amount;
+        // Already counted in the registry's active weight under the old address
+        validator.voting_weight = old_validator.voting_weight;
+        validator.authority = ctx.accounts.authority.key();
+        validator.profile = ctx.accounts.profile.key();
+        validator.migrated_at = Clock::get()?.unix_timestamp;
+        validator.bump = ctx.bumps.validator;
 
-        let now = Clock::get()?.unix_timestamp;
+        let id = validator.id;
+        let key = validator.key();
+        ctx.accounts.registry_page.replace(id, key);
 
-        // Transfer tokens from user ATA to vault ATA
-        let cpi_ctx = CpiContext::new(
+        let profile = &mut ctx.accounts.profile;
+        profile.validator_count = profile
+            .validator_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+        profile.pending_migrations = profile.pending_migrations.saturating_sub(1);
+
+        msg!(
+            "Migrated validator {} from {} to {}",
+            validator.id,
+            old_validator.key(),
+            validator.key()
+        );
+        Ok(())
+    }
+
+    pub fn migrate_stake_vault<'info>(
+        ctx: Context<'_, '_, '_, 'info, MigrateStakeVault<'info>>,
+    ) -> Result<()> {
+        let old_stake_vault = &ctx.accounts.old_stake_vault;
+        let amount = ctx.accounts.old_vault_ata.amount;
+
+        // Old vault PDA signs for its own ATA
+        let previous_authority = ctx.accounts.profile.previous_authority;
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"stake-vault",
+            previous_authority.as_ref(),
+            &[old_stake_vault.bump],
+        ]];
+
+        if amount > 0 {
+            transfer_checked_with_hook(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.old_vault_ata.to_account_info(),
+                        mint: ctx.accounts.stake_mint.to_account_info(),
+                        to: ctx.accounts.vault_ata.to_account_info(),
+                        authority: old_stake_vault.to_account_info(),
+                    },
+                    signer_seeds,
+                )
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+                amount,
+                ctx.accounts.stake_mint.decimals,
+            )?;
+        }
+
+        token_interface::close_account(CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_ata.to_account_info(),
-                to: ctx.accounts.vault_ata.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.old_vault_ata.to_account_info(),
+                destination: ctx.accounts.authority.to_account_info(),
+                authority: old_stake_vault.to_account_info(),
             },
-        );
-        token::transfer(cpi_ctx, amount)?;
+            signer_seeds,
+        ))?;
 
-        // Record staking data
-        stake_vault.owner = ctx.accounts.user.key();
+        let stake_vault = &mut ctx.accounts.stake_vault;
+        stake_vault.owner = ctx.accounts.authority.key();
         stake_vault.profile = ctx.accounts.profile.key();
         stake_vault.vault = ctx.accounts.vault_ata.key();
-        stake_vault.amount = amount;
-        stake_vault.start_stake_time = now;
-        stake_vault.reward_collected = 0;
-        stake_vault.pool = ctx.accounts.pool.key();
+        stake_vault.pool = old_stake_vault.pool;
+        stake_vault.amount = old_stake_vault.amount;
+        stake_vault.reward_collected = old_stake_vault.reward_collected;
+        stake_vault.start_stake_time = old_stake_vault.start_stake_time;
+        // The old vault may already have voted on open proposals; the new one only counts for later ones
+        stake_vault.weight_since = Clock::get()?.unix_timestamp;
         stake_vault.bump = ctx.bumps.stake_vault;
 
-        // Update pool stats
-        let pool = &mut ctx.accounts.pool;
-        pool.total_staked = pool.total_staked.checked_add(amount).unwrap();
+        // Only vaults holding tokens were counted as pending by the rotation
+        if stake_vault.amount > 0 {
+            let profile = &mut ctx.accounts.profile;
+            profile.active_stakes = profile
+                .active_stakes
+                .checked_add(1)
+                .ok_or(CustomError::MathOverflow)?;
+            profile.pending_migrations = profile.pending_migrations.saturating_sub(1);
+        }
 
         msg!(
-            "Staked {} tokens at time {} into pool {}",
-            amount,
-            now,
-            pool.id
+            "Migrated stake vault {} -> {} ({} tokens)",
+            old_stake_vault.key(),
+            stake_vault.key(),
+            amount
         );
         Ok(())
     }
 
-    pub fn unstake_tokens(ctx: Context<UnstakeTokens>) -> Result<()> {
-        let stake_vault = &mut ctx.accounts.stake_vault;
+    pub fn create_session(
+        ctx: Context<CreateSession>,
+        session_key: Pubkey,
+        scopes: u8,
+        expires_at: i64,
+    ) -> Result<()> {
         let now = Clock::get()?.unix_timestamp;
-        let pool = &ctx.accounts.pool;
 
         require!(
-            now >= stake_vault.start_stake_time + pool.lock_period as i64,
-            CustomError::StakeLocked
+            scopes != 0 && scopes & !SessionKey::ALL_SCOPES == 0,
+            CustomError::InvalidSessionScopes
+        );
+        require!(
+            expires_at > now && expires_at <= now + SessionKey::MAX_DURATION,
+            CustomError::InvalidDeadline
         );
 
-        let amount = stake_vault.amount;
-        require!(amount > 0, CustomError::ZeroStake);
-
-        // Fix temporary borrow issue
-        let user_key = ctx.accounts.user.key();
-        let bump = ctx.bumps.stake_vault;
-        let signer_seeds: &[&[u8]] = &[b"stake-vault", user_key.as_ref(), &[bump]];
-        let signer: &[&[&[u8]]] = &[signer_seeds];
+        let session = &mut ctx.accounts.session;
+        session.profile = ctx.accounts.profile.key();
+        session.authority = ctx.accounts.authority.key();
+        session.session_key = session_key;
+        session.scopes = scopes;
+        session.expires_at = expires_at;
+        session.bump = ctx.bumps.session;
 
-        // Use already borrowed `stake_vault` here
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_ata.to_account_info(),
-                to: ctx.accounts.user_ata.to_account_info(),
-                authority: stake_vault.to_account_info(),
-            },
-            signer,
+        msg!(
+            "Session {} for {} (scopes {:#05b}) expires at {}",
+            session_key,
+            session.authority,
+            scopes,
+            expires_at
         );
+        Ok(())
+    }
+
+    pub fn revoke_session(ctx: Context<RevokeSession>) -> Result<()> {
+        msg!("Revoked session {}", ctx.accounts.session.session_key);
+        Ok(())
+    }
 
-        token::transfer(cpi_ctx, amount)?;
+    pub fn create_mint(
+        ctx: Context<CreateMint>,
+        max_supply: u64,
+        validator_grant: u64,
+        epoch_duration: i64,
+        initial_epoch_emission: u64,
+        halving_interval: u64,
+    ) -> Result<()> {
+        // Policy is fixed by the upgrade authority on the first call; later calls leave it untouched
+        let config = &mut ctx.accounts.mint_config;
+        if config.admin == Pubkey::default() {
+            require!(
+                max_supply > 0
+                    && validator_grant <= max_supply
+                    && epoch_duration > 0
+                    && initial_epoch_emission > 0,
+                CustomError::InvalidMintConfig
+            );
 
-        stake_vault.amount = 0;
-        stake_vault.start_stake_time = 0;
+            config.admin = ctx.accounts.payer.key();
+            config.mint = ctx.accounts.mint.key();
+            config.max_supply = max_supply;
+            config.validator_grant = validator_grant;
+            config.total_minted = ctx.accounts.mint.supply;
+            config.emission_start = Clock::get()?.unix_timestamp;
+            config.epoch_duration = epoch_duration;
+            config.initial_epoch_emission = initial_epoch_emission;
+            config.halving_interval = halving_interval;
+            config.current_epoch = 0;
+            config.minted_this_epoch = 0;
+            config.bump = ctx.bumps.mint_config;
+
+            msg!(
+                "Mint config: max supply {}, validator grant {}, {} per {}s epoch",
+                max_supply,
+                validator_grant,
+                initial_epoch_emission,
+                epoch_duration
+            );
+        }
 
-        msg!("Unstaked {} tokens at time {}", amount, now);
         Ok(())
     }
 
-    pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
-        let stake_vault = &mut ctx.accounts.stake_vault;
-        let now = Clock::get()?.unix_timestamp;
-
-        let elapsed = now
-            .checked_sub(stake_vault.start_stake_time)
-            .ok_or(CustomError::TimeCalculationFailed)?;
+    pub fn init_validator_registry(ctx: Context<InitValidatorRegistry>) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        registry.next_id = 0;
+        registry.validator_count = 0;
+        registry.active_count = 0;
+        registry.total_weight = 0;
+        registry.active_weight = 0;
+        registry.bump = ctx.bumps.registry;
+        Ok(())
+    }
 
-        let reward_rate = ctx.accounts.pool.reward_per_second;
-        let total_reward = elapsed as u64 * reward_rate;
-        let pending = total_reward.saturating_sub(stake_vault.reward_collected);
+    /// Registers a validator under the next sequential id
+    pub fn init_validator(ctx: Context<InitValidator>, name: String) -> Result<()> {
+        let registry = &mut ctx.accounts.registry;
+        let id = registry.next_id;
+        registry.next_id = id.checked_add(1).ok_or(CustomError::MathOverflow)?;
+        registry.validator_count = registry
+            .validator_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let page = &mut ctx.accounts.registry_page;
+        page.page = RegistryPage::page_of(id);
+        page.bump = ctx.bumps.registry_page;
+        page.validators.push(ctx.accounts.validator.key());
 
-        require!(pending > 0, CustomError::NoRewardAvailable);
+        let validator = &mut ctx.accounts.validator;
+        validator.id = id;
+        validator.name = name;
+        validator.status = ValidatorStatus::Pending;
+        validator.authority = ctx.accounts.authority.key();
+        validator.profile = ctx.accounts.profile.key();
+        validator.bump = ctx.bumps.validator;
 
-        let vault_balance = ctx.accounts.reward_vault.amount;
-        require!(
-            vault_balance >= pending,
-            CustomError::InsufficientRewardVault
-        );
+        let profile = &mut ctx.accounts.profile;
+        profile.validator_count = profile
+            .validator_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        // Pending validators are expected to heartbeat too; the crank counts missed
+        // epochs from here once they are approved
+        let epoch_length = ctx.accounts.validator_config.epoch_length;
+        let uptime = &mut ctx.accounts.uptime;
+        uptime.validator_id = id;
+        uptime.epoch_length = epoch_length;
+        uptime.start_epoch = uptime.epoch_at(Clock::get()?.unix_timestamp);
+        uptime.last_epoch = uptime.start_epoch;
+        uptime.bump = ctx.bumps.uptime;
+
+        let config = &mut ctx.accounts.mint_config;
+        let grant = config.validator_grant;
+        config.record_mint(grant, Clock::get()?.unix_timestamp)?;
+        ctx.accounts.validator.grant_amount = grant;
 
         let bump = ctx.bumps.mint_authority;
         let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
@@ -313,2139 +540,7471 @@ pub mod smartolana {
         let cpi_ctx = CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
             MintTo {
-                mint: ctx.accounts.reward_mint.to_account_info(),
-                to: ctx.accounts.user_reward_ata.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.validator_ata.to_account_info(),
                 authority: ctx.accounts.mint_authority.to_account_info(),
             },
             signer_seeds,
         );
 
-        token::mint_to(cpi_ctx, pending)?;
-        stake_vault.reward_collected += pending;
+        token_interface::mint_to(cpi_ctx, grant)?;
+        Ok(())
+    }
 
-        // Deduct from internal reward balance
-        ctx.accounts.pool.reward_balance = ctx.accounts.pool.reward_balance.saturating_sub(pending);
+    pub fn update_validator(ctx: Context<UpdateValidator>, new_name: String) -> Result<()> {
+        let validator = &mut ctx.accounts.validator;
+        validator.name = new_name;
+        Ok(())
+    }
 
-        msg!(
-            "Minted {} tokens as reward to user {} from pool {}",
-            pending,
-            ctx.accounts.user.key(),
-            ctx.accounts.pool.id
+    pub fn approve_validator(ctx: Context<ApproveValidator>) -> Result<()> {
+        let validator = &mut ctx.accounts.validator;
+        require!(
+            validator.status == ValidatorStatus::Pending,
+            CustomError::InvalidValidatorStatus
         );
+        validator.status = ValidatorStatus::Active;
 
+        let registry = &mut ctx.accounts.registry;
+        registry.active_count = registry
+            .active_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!("Validator {} approved", validator.key());
         Ok(())
     }
 
-    pub fn init_staking_pool(
-        ctx: Context<InitStakingPool>,
-        id: u64,
-        name: String,
-        reward_per_second: u64,
-        lock_period: u64,
-    ) -> Result<()> {
-        let pool = &mut ctx.accounts.pool;
-        pool.id = id;
-        pool.name = name;
-        pool.authority = ctx.accounts.authority.key();
-        pool.stake_mint = ctx.accounts.stake_mint.key();
-        pool.reward_mint = ctx.accounts.reward_mint.key();
-        pool.reward_per_second = reward_per_second;
-        pool.total_staked = 0;
-        pool.lock_period = lock_period;
-        pool.bump = ctx.bumps.pool;
-        pool.reward_vault = ctx.accounts.reward_vault.key();
-        pool.reward_vault_authority_bump = ctx.bumps.reward_vault_authority;
+    /// Starts unbonding; the validator stops voting and taking delegations. Until the
+    /// unbonding period and any jail time run out it can still be slashed, its
+    /// delegators cannot undelegate or redelegate away, and the operator's own stake
+    /// stays bonded until `close_validator`
+    pub fn begin_exit(ctx: Context<BeginExit>) -> Result<()> {
+        let validator = &mut ctx.accounts.validator;
+        require!(
+            matches!(
+                validator.status,
+                ValidatorStatus::Pending
+                    | ValidatorStatus::Active
+                    | ValidatorStatus::Jailed
+                    | ValidatorStatus::Inactive
+            ),
+            CustomError::InvalidValidatorStatus
+        );
+
+        if validator.status == ValidatorStatus::Active {
+            let registry = &mut ctx.accounts.registry;
+            registry.active_count = registry.active_count.saturating_sub(1);
+        }
 
+        let now = Clock::get()?.unix_timestamp;
+        validator.status = ValidatorStatus::Exiting;
+        ctx.accounts.registry.set_voting_weight(validator, 0);
+        validator.unbonding_until = now
+            .checked_add(ctx.accounts.validator_config.unbonding_period)
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!(
+            "Validator {} exiting, closable from {}",
+            validator.key(),
+            validator.unbonding_until
+        );
         Ok(())
     }
 
-    pub fn refill_pool(ctx: Context<RefillPool>, amount: u64) -> Result<()> {
-        // Transfer tokens from admin_ata -> reward_vault
-        token::transfer(
-            CpiContext::new(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.admin_ata.to_account_info(),
-                    to: ctx.accounts.reward_vault.to_account_info(),
-                    authority: ctx.accounts.admin.to_account_info(),
-                },
-            ),
-            amount,
-        )?;
-
-        // Track internal reward balance
-        let pool = &mut ctx.accounts.pool;
-        pool.reward_balance = pool.reward_balance.saturating_add(amount);
+    /// Burns the validator grant from the authority's ATA before closing, failing
+    /// with `GrantNotHeld` if the tokens have been moved or spent. A migrated
+    /// validator owes the grant from the new authority's ATA, so the tokens left in
+    /// the previous authority's ATA must be moved over first. The burn does not
+    /// give back supply cap room
+    pub fn close_validator(ctx: Context<CloseValidator>) -> Result<()> {
+        let validator = &ctx.accounts.validator;
+        require!(
+            validator.status == ValidatorStatus::Exiting,
+            CustomError::InvalidValidatorStatus
+        );
+        let now = Clock::get()?.unix_timestamp;
+        require!(
+            now >= validator.unbonding_until && now >= validator.jailed_until,
+            CustomError::ValidatorUnbonding
+        );
 
-        msg!(
-            "Refilled pool {} with {} tokens into reward vault",
-            pool.id,
-            amount
+        // The grant minted at init goes back, so opening and closing validators
+        // cannot farm tokens
+        let grant = validator.grant_amount;
+        require!(
+            ctx.accounts.validator_ata.amount >= grant,
+            CustomError::GrantNotHeld
         );
+        if grant > 0 {
+            token_interface::burn(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Burn {
+                        mint: ctx.accounts.mint.to_account_info(),
+                        from: ctx.accounts.validator_ata.to_account_info(),
+                        authority: ctx.accounts.authority.to_account_info(),
+                    },
+                ),
+                grant,
+            )?;
+        }
 
+        let id = ctx.accounts.validator.id;
+        ctx.accounts.registry_page.remove(id);
+        let registry = &mut ctx.accounts.registry;
+        registry.validator_count = registry.validator_count.saturating_sub(1);
+
+        let profile = &mut ctx.accounts.profile;
+        profile.validator_count = profile.validator_count.saturating_sub(1);
         Ok(())
     }
 
-    pub fn update_pool_config(
-        ctx: Context<UpdatePoolConfig>,
-        new_rate: u64,
-        new_lock_period: u64,
-        pause: bool,
+    pub fn transfer_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, TransferTokens<'info>>,
+        amount: u64,
     ) -> Result<()> {
-        let pool = &mut ctx.accounts.staking_pool;
-
-        pool.reward_per_second = new_rate;
-        pool.lock_period = new_lock_period;
-        pool.paused = pause;
-
-        msg!(
-            "Updated pool config → rate: {}, lock: {}, paused: {}",
-            new_rate,
-            new_lock_period,
-            pause
+        // Manual verification that the sender owns the 'from' ATA
+        require!(
+            ctx.accounts.from.owner == ctx.accounts.sender.key(),
+            CustomError::Unauthorized
         );
 
+        let decimals = ctx.accounts.mint.decimals;
+        transfer_checked_with_hook(
+            ctx.accounts
+                .into_transfer_context()
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount,
+            decimals,
+        )?;
         Ok(())
     }
 
-    pub fn init_escrow(
-        ctx: Context<InitEscrow>,
-        amount_offered: u64,
-        amount_expected: u64,
-        unlock_at: i64,
+    /// Pays `amounts[i]` to the i-th `[wallet, ata, blocklist entry]` triple in remaining accounts.
+    /// Any accounts after the triples are forwarded to every transfer for the mint's transfer hook
+    pub fn batch_transfer<'info>(
+        ctx: Context<'_, '_, '_, 'info, BatchTransfer<'info>>,
+        amounts: Vec<u64>,
+        create_missing_atas: bool,
     ) -> Result<()> {
-        let clock = Clock::get()?;
         require!(
-            unlock_at > clock.unix_timestamp,
-            CustomError::InvalidDeadline
+            !amounts.is_empty() && amounts.len() <= BatchTransfer::MAX_RECIPIENTS,
+            CustomError::InvalidBatch
         );
+        require!(
+            ctx.remaining_accounts.len() >= amounts.len() * 3,
+            CustomError::InvalidBatch
+        );
+        let (recipients, hook_accounts) = ctx.remaining_accounts.split_at(amounts.len() * 3);
 
-        let escrow = &mut ctx.accounts.escrow;
+        let mint_key = ctx.accounts.mint.key();
+        let token_program_key = ctx.accounts.token_program.key();
+        let decimals = ctx.accounts.mint.decimals;
 
-        // Transfer offered tokens into vault PDA
-        let cpi_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx
-                    .accounts
-                    .initializer_deposit_token_account
-                    .to_account_info(),
-                to: ctx.accounts.vault_amount.to_account_info(),
-                authority: ctx.accounts.initializer.to_account_info(),
-            },
-        );
-        token::transfer(cpi_ctx, amount_offered)?;
+        for (index, (amount, recipient)) in amounts.iter().zip(recipients.chunks(3)).enumerate() {
+            let (wallet, ata, entry) = (&recipient[0], &recipient[1], &recipient[2]);
 
-        escrow.initializer = ctx.accounts.initializer.key();
-        escrow.initializer_deposit_token_account =
-            ctx.accounts.initializer_deposit_token_account.key();
-        escrow.vault_amount = ctx.accounts.vault_amount.key();
-        escrow.mint_offered = ctx.accounts.mint_offered.key();
-        escrow.mint_expected = ctx.accounts.mint_expected.key();
-        escrow.amount_offered = amount_offered;
-        escrow.amount_expected = amount_expected;
-        escrow.unlock_at = unlock_at;
-        escrow.is_fulfilled = false;
-        escrow.bump = ctx.bumps.escrow;
+            require_keys_eq!(
+                entry.key(),
+                Pubkey::find_program_address(
+                    &[b"blocklist-entry".as_ref(), wallet.key.as_ref()],
+                    ctx.program_id
+                )
+                .0,
+                CustomError::InvalidRecipientAccount
+            );
+            require!(
+                BlocklistEntry::allows(&mint_key, entry),
+                CustomError::Blocklisted
+            );
+            require_keys_eq!(
+                ata.key(),
+                associated_token::get_associated_token_address_with_program_id(
+                    wallet.key,
+                    &mint_key,
+                    &token_program_key,
+                ),
+                CustomError::InvalidRecipientAccount
+            );
+
+            let created_ata = ata.data_is_empty();
+            if created_ata {
+                require!(create_missing_atas, CustomError::InvalidRecipientAccount);
+                associated_token::create(CpiContext::new(
+                    ctx.accounts.associated_token_program.to_account_info(),
+                    Create {
+                        payer: ctx.accounts.sender.to_account_info(),
+                        associated_token: ata.clone(),
+                        authority: wallet.clone(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        system_program: ctx.accounts.system_program.to_account_info(),
+                        token_program: ctx.accounts.token_program.to_account_info(),
+                    },
+                ))?;
+            } else {
+                // Legacy ATAs can have their owner reassigned, so check the live state
+                require_keys_eq!(
+                    *ata.owner,
+                    token_program_key,
+                    CustomError::InvalidRecipientAccount
+                );
+                let account = TokenAccount::try_deserialize(&mut &ata.try_borrow_data()?[..])?;
+                require!(
+                    account.mint == mint_key && account.owner == wallet.key(),
+                    CustomError::InvalidRecipientAccount
+                );
+            }
+
+            transfer_checked_with_hook(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.from.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ata.clone(),
+                        authority: ctx.accounts.sender.to_account_info(),
+                    },
+                )
+                .with_remaining_accounts(hook_accounts.to_vec()),
+                *amount,
+                decimals,
+            )?;
+
+            emit!(BatchTransferred {
+                mint: mint_key,
+                sender: ctx.accounts.sender.key(),
+                index: index as u16,
+                recipient: wallet.key(),
+                ata: ata.key(),
+                amount: *amount,
+                created_ata,
+            });
+        }
 
         Ok(())
     }
 
-    pub fn fulfill_escrow(ctx: Context<FulfillEscrow>) -> Result<()> {
-        let clock = Clock::get()?;
-        let escrow = &mut ctx.accounts.escrow;
-
-        require!(!escrow.is_fulfilled, CustomError::AlreadyFulfilled);
+    pub fn approve_allowance(
+        ctx: Context<ApproveAllowance>,
+        cap: u64,
+        period_limit: u64,
+        period_duration: i64,
+    ) -> Result<()> {
         require!(
-            clock.unix_timestamp >= escrow.unlock_at,
-            CustomError::StakeLocked
+            cap > 0 && period_limit > 0 && period_limit <= cap && period_duration > 0,
+            CustomError::InvalidAllowance
         );
 
-        // Transfer expected tokens from taker -> initializer
-        let pay_initializer_ctx = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.taker_payment_ata.to_account_info(),
-                to: ctx.accounts.initializer_receive_ata.to_account_info(),
-                authority: ctx.accounts.taker.to_account_info(),
-            },
-        );
-        token::transfer(pay_initializer_ctx, escrow.amount_expected)?;
+        // One delegate PDA per owner backs all of their allowances, so the SPL
+        // delegation is topped up by this cap rather than replaced
+        let source = &ctx.accounts.source;
+        let delegated = if source.delegate == COption::Some(ctx.accounts.delegate.key()) {
+            source.delegated_amount
+        } else {
+            0
+        };
+        token_interface::approve(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Approve {
+                    to: source.to_account_info(),
+                    delegate: ctx.accounts.delegate.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ),
+            delegated.saturating_add(cap),
+        )?;
 
-        // Transfer offered tokens from vault -> taker
-        let bump = ctx.bumps.vault_authority;
-        let signer_seeds: &[&[&[u8]]] =
-            &[&[b"vault-authority", escrow.initializer.as_ref(), &[bump]]];
+        let allowance = &mut ctx.accounts.allowance;
+        allowance.owner = ctx.accounts.owner.key();
+        allowance.spender = ctx.accounts.spender.key();
+        allowance.mint = ctx.accounts.mint.key();
+        allowance.source = ctx.accounts.source.key();
+        allowance.cap = cap;
+        allowance.spent = 0;
+        allowance.period_limit = period_limit;
+        allowance.period_duration = period_duration;
+        allowance.period_start = Clock::get()?.unix_timestamp;
+        allowance.period_spent = 0;
+        allowance.delegate_bump = ctx.bumps.delegate;
+        allowance.bump = ctx.bumps.allowance;
 
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_amount.to_account_info(),
-                to: ctx.accounts.taker_receive_ata.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-            },
-            signer_seeds,
+        msg!(
+            "Allowance for {}: {} total, {} per {}s",
+            allowance.spender,
+            cap,
+            period_limit,
+            period_duration
         );
-        token::transfer(cpi_ctx, escrow.amount_offered)?;
-
-        escrow.is_fulfilled = true;
-
         Ok(())
     }
 
-    pub fn cancel_escrow(ctx: Context<CancelEscrow>) -> Result<()> {
-        let escrow = &mut ctx.accounts.escrow;
-
-        require!(!escrow.is_fulfilled, CustomError::AlreadyFulfilled);
-        require!(
-            ctx.accounts.initializer.key() == escrow.initializer,
-            CustomError::Unauthorized
-        );
+    pub fn revoke_allowance(ctx: Context<RevokeAllowance>) -> Result<()> {
+        let allowance = &ctx.accounts.allowance;
+        let unspent = allowance.cap - allowance.spent;
 
-        // Return locked tokens back to initializer
-        let bump = ctx.bumps.vault_authority;
-        let signer_seeds: &[&[&[u8]]] =
-            &[&[b"vault-authority", escrow.initializer.as_ref(), &[bump]]];
+        // Hand back only this allowance's share of the delegation
+        let source = &ctx.accounts.source;
+        let remaining = if source.delegate == COption::Some(ctx.accounts.delegate.key()) {
+            source.delegated_amount.saturating_sub(unspent)
+        } else {
+            0
+        };
 
-        let cpi_ctx = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_amount.to_account_info(),
-                to: ctx.accounts.initializer_receive_ata.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-            },
-            signer_seeds,
-        );
-        token::transfer(cpi_ctx, escrow.amount_offered)?;
+        if remaining == 0 {
+            token_interface::revoke(CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Revoke {
+                    source: source.to_account_info(),
+                    authority: ctx.accounts.owner.to_account_info(),
+                },
+            ))?;
+        } else {
+            token_interface::approve(
+                CpiContext::new(
+                    ctx.accounts.token_program.to_account_info(),
+                    Approve {
+                        to: source.to_account_info(),
+                        delegate: ctx.accounts.delegate.to_account_info(),
+                        authority: ctx.accounts.owner.to_account_info(),
+                    },
+                ),
+                remaining,
+            )?;
+        }
 
+        msg!("Revoked allowance for {}", allowance.spender);
         Ok(())
     }
 
-    pub fn swap(
-        ctx: Context<Swap>,
-        amount_in: u64,
-        min_amount_out: u64,
-        max_amount_in: u64,
+    /// Spender pulls funds within its allowance; the owner's delegate PDA signs
+    pub fn transfer_from<'info>(
+        ctx: Context<'_, '_, '_, 'info, TransferFrom<'info>>,
+        amount: u64,
     ) -> Result<()> {
-        let pool = &ctx.accounts.pool;
+        let now = Clock::get()?.unix_timestamp;
+        let allowance = &mut ctx.accounts.allowance;
 
-        let reserve_a = ctx.accounts.input_vault.amount;
-        let reserve_b = ctx.accounts.output_vault.amount;
+        allowance.spend(amount, now)?;
 
-        // Compute Output
-        let amount_in_with_fee = amount_in * 997;
-        let numerator = amount_in_with_fee * reserve_b;
-        let denominator = (reserve_a * 1000) + amount_in_with_fee;
-        let amount_out = numerator / denominator;
+        let owner = allowance.owner;
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"allowance-delegate",
+            owner.as_ref(),
+            &[allowance.delegate_bump],
+        ]];
 
-        // Slippage protection
-        require!(amount_out >= min_amount_out, CustomError::SlippageExceeded);
-        require!(amount_in <= max_amount_in, CustomError::SlippageExceeded);
+        transfer_checked_with_hook(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.source.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.destination.to_account_info(),
+                    authority: ctx.accounts.delegate.to_account_info(),
+                },
+                signer_seeds,
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
 
-        // Transfer Token A from user -> vault
-        let cpi_ctx_in = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_input_token_account.to_account_info(),
-                to: ctx.accounts.input_vault.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
+        msg!(
+            "{} pulled {} from {} ({} left this period)",
+            ctx.accounts.spender.key(),
+            amount,
+            owner,
+            allowance.period_limit - allowance.period_spent
         );
-        token::transfer(cpi_ctx_in, amount_in)?;
-
-        // Transfer Token B from vault -> user
-        let bump = ctx.bumps.vault_authority;
-        let pool_key = pool.key();
-        let token_out_key = ctx.accounts.output_token_mint.key();
-        let signer_seeds: &[&[&[u8]]] =
-            &[&[b"vault", pool_key.as_ref(), token_out_key.as_ref(), &[bump]]];
+        Ok(())
+    }
 
-        let cpi_ctx_out = CpiContext::new_with_signer(
+    pub fn burn_tokens(ctx: Context<BurnTokens>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.output_vault.to_account_info(),
-                to: ctx.accounts.user_output_token_account.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
+            Burn {
+                mint: ctx.accounts.mint.to_account_info(),
+                from: ctx.accounts.owner_ata.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
             },
-            signer_seeds,
         );
-        token::transfer(cpi_ctx_out, amount_out)?;
 
-        msg!(
-            "Swapped {} → {} (min expected {}, max allowed in {})",
-            amount_in,
-            amount_out,
-            min_amount_out,
-            max_amount_in
+        token_interface::burn(cpi_ctx, amount)?;
+        Ok(())
+    }
+
+    pub fn propose_mint_authority(
+        ctx: Context<ProposeMintAuthority>,
+        new_mint_authority: Pubkey,
+        new_freeze_authority: Pubkey,
+    ) -> Result<()> {
+        require!(
+            new_mint_authority != Pubkey::default() && new_freeze_authority != Pubkey::default(),
+            CustomError::InvalidNewAuthority
         );
 
+        let now = Clock::get()?.unix_timestamp;
+        let config = &mut ctx.accounts.mint_config;
+        config.pending_mint_authority = new_mint_authority;
+        config.pending_freeze_authority = new_freeze_authority;
+        config.handover_eta = now + MintConfig::HANDOVER_TIMELOCK;
+
+        emit!(MintAuthorityProposed {
+            mint: config.mint,
+            new_mint_authority,
+            new_freeze_authority,
+            eta: config.handover_eta,
+        });
         Ok(())
     }
 
-    pub fn add_liquidity(ctx: Context<AddLiquidity>, amount_a: u64, amount_b: u64) -> Result<()> {
-        let pool = &mut ctx.accounts.pool;
-        let reserve_a = ctx.accounts.token_a_vault.amount;
-        let reserve_b = ctx.accounts.token_b_vault.amount;
+    pub fn cancel_mint_authority(ctx: Context<ProposeMintAuthority>) -> Result<()> {
+        let config = &mut ctx.accounts.mint_config;
+        require!(config.handover_eta != 0, CustomError::NoHandoverPending);
 
-        let total_lp_supply = pool.total_lp_supply;
+        let new_mint_authority = config.pending_mint_authority;
+        config.clear_pending_handover();
 
-        // Step 1 - Transfer user tokens into vaults
-        let cpi_ctx_a = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_token_a.to_account_info(),
-                to: ctx.accounts.token_a_vault.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
-        );
-        token::transfer(cpi_ctx_a, amount_a)?;
+        emit!(MintAuthorityCancelled {
+            mint: config.mint,
+            new_mint_authority,
+        });
+        Ok(())
+    }
 
-        // Step 2 - Transfer token B from user to vault
-        let cpi_ctx_b = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_token_b.to_account_info(),
-                to: ctx.accounts.token_b_vault.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
-        );
-        token::transfer(cpi_ctx_b, amount_b)?;
+    /// Completes the handover; the incoming mint authority must sign, proving it can
+    pub fn accept_mint_authority(ctx: Context<AcceptMintAuthority>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let config = &ctx.accounts.mint_config;
 
-        // Step 3 - Calculate LP tokens to mint
-        let lp_to_mint = if total_lp_supply == 0 {
-            // Frist LP - gets sqrt(amount_a * amount_b)
-            integer_sqrt(amount_a.checked_mul(amount_b).unwrap())
-        } else {
-            let lp_from_a = amount_a * total_lp_supply / reserve_a;
-            let lp_from_b = amount_b * total_lp_supply / reserve_b;
-            lp_from_a.min(lp_from_b)
-        };
+        require!(config.handover_eta != 0, CustomError::NoHandoverPending);
+        require!(now >= config.handover_eta, CustomError::HandoverTimelocked);
 
-        // Step 4 - Mint LP tokens to user using vault PDA as mint authority
-        let bump = ctx.bumps.vault_authority;
-        let pool_key = pool.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"vault-authority", pool_key.as_ref(), &[bump]]];
+        let new_mint_authority = config.pending_mint_authority;
+        let new_freeze_authority = config.pending_freeze_authority;
 
-        let cpi_ctx_mint = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            MintTo {
-                mint: ctx.accounts.lp_mint.to_account_info(),
-                to: ctx.accounts.user_lp_token_account.to_account_info(),
-                authority: ctx.accounts.vault_authority.to_account_info(),
-            },
-            signer_seeds,
-        );
-        token::mint_to(cpi_ctx_mint, lp_to_mint)?;
+        let bump = ctx.bumps.mint_authority;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
 
-        // Step 5 - Update LP supply in pool
-        pool.total_lp_supply = pool.total_lp_supply.checked_add(lp_to_mint).unwrap();
+        // Freeze authority goes first: once MintTokens moves, the PDA can no longer sign
+        for (authority_type, new_authority) in [
+            (AuthorityType::FreezeAccount, new_freeze_authority),
+            (AuthorityType::MintTokens, new_mint_authority),
+        ] {
+            set_authority(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    SetAuthority {
+                        current_authority: ctx.accounts.mint_authority.to_account_info(),
+                        account_or_mint: ctx.accounts.mint.to_account_info(),
+                    },
+                    signer_seeds,
+                ),
+                authority_type,
+                Some(new_authority),
+            )?;
+        }
 
+        let config = &mut ctx.accounts.mint_config;
+        config.clear_pending_handover();
+
+        emit!(MintAuthorityAccepted {
+            mint: config.mint,
+            new_mint_authority,
+            new_freeze_authority,
+        });
         Ok(())
     }
 
-    pub fn remove_liquidity(ctx: Context<RemoveLiquidity>, lp_amount: u64) -> Result<()> {
-        let pool = &mut ctx.accounts.pool;
-        let reserve_a = ctx.accounts.token_a_vault.amount;
-        let reserve_b = ctx.accounts.token_b_vault.amount;
+    pub fn init_mint_metadata(
+        ctx: Context<InitMintMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        MintMetadata::validate(&name, &symbol, &uri)?;
 
-        let total_lp_supply = pool.total_lp_supply;
-        require!(total_lp_supply > 0, CustomError::NoLiquidity);
+        let metadata = &mut ctx.accounts.metadata;
+        metadata.mint = ctx.accounts.mint.key();
+        metadata.update_authority = ctx.accounts.admin.key();
+        metadata.name = name;
+        metadata.symbol = symbol;
+        metadata.uri = uri;
+        metadata.bump = ctx.bumps.metadata;
 
-        // Calculate the share of tokens to return
-        let amount_a = lp_amount
-            .checked_mul(reserve_a)
-            .unwrap()
-            .checked_div(total_lp_supply)
-            .unwrap();
+        msg!(
+            "Metadata for {}: {} ({})",
+            metadata.mint,
+            metadata.name,
+            metadata.symbol
+        );
+        Ok(())
+    }
 
-        let amount_b = lp_amount
-            .checked_mul(reserve_b)
-            .unwrap()
-            .checked_div(total_lp_supply)
-            .unwrap();
+    pub fn update_mint_metadata(
+        ctx: Context<UpdateMintMetadata>,
+        name: String,
+        symbol: String,
+        uri: String,
+    ) -> Result<()> {
+        MintMetadata::validate(&name, &symbol, &uri)?;
 
-        // Step 1 - Burn LP tokens from user
-        let cpi_ctx_burn = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Burn {
-                mint: ctx.accounts.lp_mint.to_account_info(),
-                from: ctx.accounts.user_lp_token.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
+        let metadata = &mut ctx.accounts.metadata;
+        metadata.name = name;
+        metadata.symbol = symbol;
+        metadata.uri = uri;
+        Ok(())
+    }
+
+    /// Hands metadata control to another key, e.g. the governance treasury PDA
+    pub fn set_mint_metadata_authority(
+        ctx: Context<UpdateMintMetadata>,
+        new_update_authority: Pubkey,
+    ) -> Result<()> {
+        let metadata = &mut ctx.accounts.metadata;
+        msg!(
+            "Metadata update authority {} -> {}",
+            metadata.update_authority,
+            new_update_authority
         );
-        token::burn(cpi_ctx_burn, lp_amount)?;
+        metadata.update_authority = new_update_authority;
+        Ok(())
+    }
 
-        // Step 2 - Transfer token A to user
-        let bump = ctx.bumps.vault_authority;
-        let pool_key = pool.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"vault-authority", pool_key.as_ref(), &[bump]]];
+    pub fn init_blocklist(ctx: Context<InitBlocklist>) -> Result<()> {
+        let blocklist = &mut ctx.accounts.blocklist;
+        blocklist.admin = ctx.accounts.admin.key();
+        blocklist.bump = ctx.bumps.blocklist;
+        Ok(())
+    }
 
-        let cpi_ctx_a = CpiContext::new_with_signer(
+    pub fn add_to_blocklist(ctx: Context<AddToBlocklist>, owner: Pubkey) -> Result<()> {
+        let entry = &mut ctx.accounts.entry;
+        entry.owner = owner;
+        entry.blocked_at = Clock::get()?.unix_timestamp;
+        entry.bump = ctx.bumps.entry;
+
+        msg!("Blocklisted {}", owner);
+        Ok(())
+    }
+
+    pub fn remove_from_blocklist(_ctx: Context<RemoveFromBlocklist>, owner: Pubkey) -> Result<()> {
+        msg!("Removed {} from blocklist", owner);
+        Ok(())
+    }
+
+    pub fn freeze_account(ctx: Context<FreezeTokenAccount>) -> Result<()> {
+        let bump = ctx.bumps.mint_authority;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
+
+        token_interface::freeze_account(CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.token_a_vault.to_account_info(),
-                to: ctx.accounts.user_token_a.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
+            token_interface::FreezeAccount {
+                account: ctx.accounts.token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
             },
             signer_seeds,
-        );
-        token::transfer(cpi_ctx_a, amount_a)?;
+        ))?;
 
-        // Step 3 - Transfer token B to user
-        let cpi_ctx_b = CpiContext::new_with_signer(
+        msg!("Froze {}", ctx.accounts.token_account.key());
+        Ok(())
+    }
+
+    pub fn thaw_account(ctx: Context<FreezeTokenAccount>) -> Result<()> {
+        let bump = ctx.bumps.mint_authority;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
+
+        token_interface::thaw_account(CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.token_b_vault.to_account_info(),
-                to: ctx.accounts.user_token_b.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
+            token_interface::ThawAccount {
+                account: ctx.accounts.token_account.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
             },
             signer_seeds,
-        );
-        token::transfer(cpi_ctx_b, amount_b)?;
-
-        // Step 4 - Update LP supply
-        pool.total_lp_supply = pool.total_lp_supply.checked_sub(lp_amount).unwrap();
+        ))?;
 
+        msg!("Thawed {}", ctx.accounts.token_account.key());
         Ok(())
     }
 
-    pub fn create_multisig(
-        ctx: Context<CreateMultisig>,
-        owners: Vec<Pubkey>,
-        threshold: u8,
+    pub fn new_distributor<'info>(
+        ctx: Context<'_, '_, '_, 'info, NewDistributor<'info>>,
+        id: u64,
+        root: [u8; 32],
+        max_total_claim: u64,
+        max_num_nodes: u64,
+        expires_at: i64,
     ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
         require!(
-            owners.len() <= Multisig::MAX_OWNERS,
-            CustomError::TooManyOwners
-        );
-        require!(
-            threshold > 0 && threshold <= owners.len() as u8,
-            CustomError::InvalidThreshold
+            max_total_claim > 0
+                && max_num_nodes > 0
+                && max_num_nodes <= Distributor::MAX_NUM_NODES
+                && expires_at > now,
+            CustomError::InvalidDistributor
         );
 
-        let multisig = &mut ctx.accounts.multisig;
+        // Fund the vault with the full drop up front
+        transfer_checked_with_hook(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.admin_ata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.admin.to_account_info(),
+                },
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            max_total_claim,
+            ctx.accounts.mint.decimals,
+        )?;
 
-        multisig.owners = owners;
-        multisig.threshold = threshold;
-        multisig.bump = ctx.bumps.multisig;
-        multisig.owner_set_seqno = 0;
+        let distributor = &mut ctx.accounts.distributor;
+        distributor.id = id;
+        distributor.admin = ctx.accounts.admin.key();
+        distributor.mint = ctx.accounts.mint.key();
+        distributor.vault = ctx.accounts.vault.key();
+        distributor.root = root;
+        distributor.max_total_claim = max_total_claim;
+        distributor.total_claimed = 0;
+        distributor.max_num_nodes = max_num_nodes;
+        distributor.num_claimed = 0;
+        distributor.expires_at = expires_at;
+        distributor.clawed_back = false;
+        distributor.authority_bump = ctx.bumps.distributor_authority;
+        distributor.bump = ctx.bumps.distributor;
+        distributor.claimed_bitmap = vec![0; Distributor::bitmap_len(max_num_nodes)];
 
+        msg!(
+            "Distributor {} funded with {} until {}",
+            distributor.key(),
+            max_total_claim,
+            expires_at
+        );
         Ok(())
     }
 
-    pub fn propose_transaction(
-        ctx: Context<ProposeTransaction>,
-        program_id: Pubkey,
-        accounts: Vec<TransactionAccount>,
-        data: Vec<u8>,
+    pub fn claim<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimDistribution<'info>>,
+        index: u64,
+        amount: u64,
+        proof: Vec<[u8; 32]>,
     ) -> Result<()> {
-        let multisig = &ctx.accounts.multisig;
-        let proposer = &ctx.accounts.proposer;
+        let now = Clock::get()?.unix_timestamp;
+        let distributor = &mut ctx.accounts.distributor;
 
-        // Validator proposer is an owner
         require!(
-            multisig.owners.contains(&proposer.key()),
-            CustomError::NotAnOwner
+            !distributor.clawed_back && now < distributor.expires_at,
+            CustomError::DistributorExpired
         );
+        require!(index < distributor.max_num_nodes, CustomError::InvalidProof);
+        require!(!distributor.is_claimed(index), CustomError::AlreadyClaimed);
 
-        // Populate transaction
-        let tx = &mut ctx.accounts.tx;
-        tx.multisig = multisig.key();
-        tx.program_id = program_id;
-        tx.accounts = accounts;
-        tx.data = data;
-        tx.did_execute = false;
-        tx.signers = [false; 10];
+        let leaf = merkle_leaf(index, &ctx.accounts.claimant.key(), amount);
+        require!(
+            merkle_verify(&proof, &distributor.root, leaf),
+            CustomError::InvalidProof
+        );
 
-        // Mark the propose as approved
-        if let Some(index) = multisig.owners.iter().position(|k| k == &proposer.key()) {
-            tx.signers[index] = true;
-        } else {
-            return Err(CustomError::NotAnOwner.into());
-        }
-        tx.owner_set_seqno = multisig.owner_set_seqno;
+        let total_claimed = distributor
+            .total_claimed
+            .checked_add(amount)
+            .ok_or(CustomError::ClaimExceedsMax)?;
+        require!(
+            total_claimed <= distributor.max_total_claim,
+            CustomError::ClaimExceedsMax
+        );
+
+        distributor.set_claimed(index);
+        distributor.total_claimed = total_claimed;
+        distributor.num_claimed = distributor
+            .num_claimed
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let distributor_key = distributor.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"distributor-authority",
+            distributor_key.as_ref(),
+            &[distributor.authority_bump],
+        ]];
+
+        transfer_checked_with_hook(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.claimant_ata.to_account_info(),
+                    authority: ctx.accounts.distributor_authority.to_account_info(),
+                },
+                signer_seeds,
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount,
+            ctx.accounts.mint.decimals,
+        )?;
 
+        msg!(
+            "Claimed {} (index {}) for {}",
+            amount,
+            index,
+            ctx.accounts.claimant.key()
+        );
         Ok(())
     }
 
-    pub fn approve_transaction(ctx: Context<ApproveTransaction>) -> Result<()> {
-        let multisig = &ctx.accounts.multisig;
-        let signer = &ctx.accounts.signer;
-        let tx = &mut ctx.accounts.tx;
-
-        // Check signer is one of the owners
-        if let Some(index) = multisig.owners.iter().position(|k| k == &signer.key()) {
-            // Ensure signer hasnt already signed
-            require!(!tx.signers[index], CustomError::AlreadySigned);
+    /// Returns whatever is left in the vault to the admin once the drop has expired
+    pub fn clawback<'info>(ctx: Context<'_, '_, '_, 'info, Clawback<'info>>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let distributor = &mut ctx.accounts.distributor;
 
-            tx.signers[index] = true;
-            Ok(())
-        } else {
-            Err(CustomError::NotAnOwner.into())
+        require!(
+            now >= distributor.expires_at,
+            CustomError::DistributorNotExpired
+        );
+        require!(!distributor.clawed_back, CustomError::DistributorExpired);
+        distributor.clawed_back = true;
+
+        let remaining = ctx.accounts.vault.amount;
+        let distributor_key = distributor.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"distributor-authority",
+            distributor_key.as_ref(),
+            &[distributor.authority_bump],
+        ]];
+
+        if remaining > 0 {
+            transfer_checked_with_hook(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.vault.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.admin_ata.to_account_info(),
+                        authority: ctx.accounts.distributor_authority.to_account_info(),
+                    },
+                    signer_seeds,
+                )
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+                remaining,
+                ctx.accounts.mint.decimals,
+            )?;
         }
-    }
-
-    pub fn execute_transaction(ctx: Context<ExecuteTransaction>) -> Result<()> {
-        let tx = &mut ctx.accounts.tx;
-        let multisig = &ctx.accounts.multisig;
 
-        // Count how many owners signed
-        let sig_count = tx
-            .signers
-            .iter()
-            .zip(multisig.owners.iter())
-            .filter(|(signed, _)| **signed)
-            .count();
+        msg!("Clawed back {} from {}", remaining, distributor_key);
+        Ok(())
+    }
 
-        // Ensure enough approvals
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateVesting<'info>>,
+        id: u64,
+        total: u64,
+        start: i64,
+        cliff: i64,
+        end: i64,
+        step_period: i64,
+        revocable: bool,
+    ) -> Result<()> {
         require!(
-            sig_count >= multisig.threshold as usize,
-            CustomError::InsufficientSignatures
+            total > 0
+                && start <= cliff
+                && cliff <= end
+                && start < end
+                && (0..=end - start).contains(&step_period),
+            CustomError::InvalidVestingSchedule
         );
 
-        // Mark tx as executed
-        tx.did_execute = true;
-
-        // CPI to the intended program
-        let account_infos: Vec<AccountInfo> = ctx.remaining_accounts.to_vec();
-
-        let ix = anchor_lang::solana_program::instruction::Instruction {
-            program_id: tx.program_id,
-            accounts: tx
-                .accounts
-                .iter()
-                .map(
-                    |acc| anchor_lang::solana_program::instruction::AccountMeta {
-                        pubkey: acc.pubkey,
-                        is_signer: acc.is_signer,
-                        is_writable: acc.is_writable,
-                    },
-                )
-                .collect(),
-            data: tx.data.clone(),
-        };
+        let vault_before = ctx.accounts.vault.amount;
+        transfer_checked_with_hook(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.grantor_ata.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.vault.to_account_info(),
+                    authority: ctx.accounts.grantor.to_account_info(),
+                },
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            total,
+            ctx.accounts.mint.decimals,
+        )?;
 
-        anchor_lang::solana_program::program::invoke(&ix, &account_infos)?;
+        // Vest what the vault actually received, net of any transfer fee
+        ctx.accounts.vault.reload()?;
+        let total = ctx
+            .accounts
+            .vault
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let vesting = &mut ctx.accounts.vesting;
+        vesting.id = id;
+        vesting.grantor = ctx.accounts.grantor.key();
+        vesting.beneficiary = ctx.accounts.beneficiary.key();
+        vesting.mint = ctx.accounts.mint.key();
+        vesting.vault = ctx.accounts.vault.key();
+        vesting.total = total;
+        vesting.claimed = 0;
+        vesting.start = start;
+        vesting.cliff = cliff;
+        vesting.end = end;
+        vesting.step_period = step_period;
+        vesting.revocable = revocable;
+        vesting.revoked_at = 0;
+        vesting.bump = ctx.bumps.vesting;
 
+        msg!(
+            "Vesting {} for {}: {} from {} (cliff {}) to {}",
+            id,
+            vesting.beneficiary,
+            total,
+            start,
+            cliff,
+            end
+        );
         Ok(())
     }
 
-    pub fn init_pool_clmm(
-        ctx: Context<InitPoolClmm>,
-        sqrt_price_x64: u128,
-        current_tick: i32,
-        tick_spacing: u16,
-        fee_rate: u16,
-    ) -> Result<()> {
-        let pool = &mut ctx.accounts.pool_clmm;
-
-        pool.token_a_mint = ctx.accounts.token_a_mint.key();
-        pool.token_b_mint = ctx.accounts.token_b_mint.key();
-        pool.vault_a = ctx.accounts.vault_a.key();
-        pool.vault_b = ctx.accounts.vault_b.key();
-
-        pool.sqrt_price_x64 = sqrt_price_x64;
-        pool.current_tick = current_tick;
-        pool.tick_spacing = tick_spacing;
-        pool.liquidity = 0;
-        pool.fee_rate = fee_rate;
-        pool.fee_growth_global_a = 0;
-        pool.fee_growth_global_b = 0;
-
-        pool.bump = ctx.bumps.pool_clmm;
-        pool.signer_bump = ctx.bumps.pool_signer;
+    pub fn claim_vested<'info>(ctx: Context<'_, '_, '_, 'info, ClaimVested<'info>>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let vesting = &mut ctx.accounts.vesting;
+
+        let claimable = vesting
+            .vested_amount(now)
+            .checked_sub(vesting.claimed)
+            .ok_or(CustomError::MathOverflow)?;
+        require!(claimable > 0, CustomError::NothingToClaim);
+        vesting.claimed = vesting
+            .claimed
+            .checked_add(claimable)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let grantor = vesting.grantor;
+        let beneficiary = vesting.beneficiary;
+        let id_bytes = vesting.id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"vesting",
+            grantor.as_ref(),
+            beneficiary.as_ref(),
+            &id_bytes,
+            &[vesting.bump],
+        ]];
+
+        transfer_checked_with_hook(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault.to_account_info(),
+                    mint: ctx.accounts.mint.to_account_info(),
+                    to: ctx.accounts.beneficiary_ata.to_account_info(),
+                    authority: ctx.accounts.vesting.to_account_info(),
+                },
+                signer_seeds,
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            claimable,
+            ctx.accounts.mint.decimals,
+        )?;
 
+        msg!("Released {} vested tokens to {}", claimable, beneficiary);
         Ok(())
     }
 
-    pub fn init_tick(ctx: Context<InitTick>, tick_index: i32) -> Result<()> {
-        let tick = &mut ctx.accounts.tick;
-        tick.tick_index = tick_index;
-        tick.liquidity_net = 0;
-        tick.fee_growth_outside_a = 0;
-        tick.fee_growth_outside_b = 0;
+    /// Stops a revocable grant; what has vested stays claimable, the rest goes back
+    pub fn revoke_vesting<'info>(
+        ctx: Context<'_, '_, '_, 'info, RevokeVesting<'info>>,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let vesting = &mut ctx.accounts.vesting;
+
+        require!(vesting.revocable, CustomError::VestingNotRevocable);
+        require!(vesting.revoked_at == 0, CustomError::VestingRevoked);
+
+        let vested = vesting.vested_amount(now);
+        let unvested = vesting
+            .total
+            .checked_sub(vested)
+            .ok_or(CustomError::MathOverflow)?;
+        vesting.total = vested;
+        vesting.revoked_at = now;
+
+        let grantor = vesting.grantor;
+        let beneficiary = vesting.beneficiary;
+        let id_bytes = vesting.id.to_le_bytes();
+        let signer_seeds: &[&[&[u8]]] = &[&[
+            b"vesting",
+            grantor.as_ref(),
+            beneficiary.as_ref(),
+            &id_bytes,
+            &[vesting.bump],
+        ]];
+
+        if unvested > 0 {
+            transfer_checked_with_hook(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.vault.to_account_info(),
+                        mint: ctx.accounts.mint.to_account_info(),
+                        to: ctx.accounts.grantor_ata.to_account_info(),
+                        authority: ctx.accounts.vesting.to_account_info(),
+                    },
+                    signer_seeds,
+                )
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+                unvested,
+                ctx.accounts.mint.decimals,
+            )?;
+        }
+
+        msg!(
+            "Revoked vesting for {}: {} returned, {} stays claimable",
+            beneficiary,
+            unvested,
+            vested
+        );
         Ok(())
     }
 
-    pub fn open_position(
-        ctx: Context<OpenPosition>,
-        liquidity: u128,
-        tick_lower: i32,
-        tick_upper: i32,
+    pub fn init_realm(
+        ctx: Context<InitRealm>,
+        min_voting_period: i64,
+        max_voting_period: i64,
+        quorum: Quorum,
+        approval_threshold_bps: u16,
+        min_proposal_weight: u64,
     ) -> Result<()> {
-        let pool = &mut ctx.accounts.pool_clmm;
-        let tick_lower_account = &mut ctx.accounts.tick_lower;
-        let tick_upper_account = &mut ctx.accounts.tick_upper;
+        Realm::validate(
+            min_voting_period,
+            max_voting_period,
+            quorum,
+            approval_threshold_bps,
+        )?;
 
-        // Validate
-        require!(tick_lower < tick_upper, CustomError::InvalidTickRange);
-        require!(liquidity > 0, CustomError::ZeroLiquidity);
+        let realm = &mut ctx.accounts.realm;
+        realm.min_voting_period = min_voting_period;
+        realm.max_voting_period = max_voting_period;
+        realm.quorum = quorum;
+        realm.approval_threshold_bps = approval_threshold_bps;
+        realm.min_proposal_weight = min_proposal_weight;
+        realm.bump = ctx.bumps.realm;
+        Ok(())
+    }
 
-        // Update ticks
-        tick_lower_account.liquidity_net = tick_lower_account
-            .liquidity_net
-            .checked_add(liquidity as i128)
-            .unwrap();
-        tick_upper_account.liquidity_net = tick_upper_account
-            .liquidity_net
-            .checked_sub(liquidity as i128)
-            .unwrap();
+    /// Only the governance treasury can sign this, so the realm changes through a
+    /// passed proposal or not at all
+    pub fn update_realm(
+        ctx: Context<UpdateRealm>,
+        min_voting_period: i64,
+        max_voting_period: i64,
+        quorum: Quorum,
+        approval_threshold_bps: u16,
+        min_proposal_weight: u64,
+    ) -> Result<()> {
+        Realm::validate(
+            min_voting_period,
+            max_voting_period,
+            quorum,
+            approval_threshold_bps,
+        )?;
 
-        // Inline liquidity-to-token calculation (placeholder for now)
-        let amount_a = (liquidity / 1000) as u64;
-        let amount_b = (liquidity / 1000) as u64;
+        let realm = &mut ctx.accounts.realm;
+        realm.min_voting_period = min_voting_period;
+        realm.max_voting_period = max_voting_period;
+        realm.quorum = quorum;
+        realm.approval_threshold_bps = approval_threshold_bps;
+        realm.min_proposal_weight = min_proposal_weight;
+        Ok(())
+    }
 
-        // Cpi - Transfer token A
-        let cpi_ctx_a = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_token_a.to_account_info(),
-                to: ctx.accounts.vault_a.to_account_info(),
-                authority: ctx.accounts.owner.to_account_info(),
-            },
+    /// `instructions` run through `execute_proposal` once the proposal passes, signed
+    /// by the governance treasury PDA
+    pub fn create_proposal(
+        ctx: Context<CreateProposal>,
+        proposal_id: u64,
+        title: String,
+        description: String,
+        deadline: i64,
+        instructions: Vec<ProposalInstruction>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            deadline > clock.unix_timestamp,
+            CustomError::InvalidDeadline
         );
-        token::transfer(cpi_ctx_a, amount_a)?;
+        ProposalInstruction::validate(&instructions)?;
 
-        // Cpi - Transfer token B
-        let cpi_ctx_b = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_token_b.to_account_info(),
-                to: ctx.accounts.vault_b.to_account_info(),
-                authority: ctx.accounts.owner.to_account_info(),
-            },
+        let realm = &ctx.accounts.realm;
+        let voting_period = deadline - clock.unix_timestamp;
+        require!(
+            voting_period >= realm.min_voting_period && voting_period <= realm.max_voting_period,
+            CustomError::InvalidVotingPeriod
         );
-        token::transfer(cpi_ctx_b, amount_b)?;
-
-        // Store user_position
-        let user_position = &mut ctx.accounts.user_position;
-        user_position.owner = ctx.accounts.owner.key();
-        user_position.pool_clmm = pool.key();
-        user_position.tick_lower = tick_lower;
-        user_position.tick_upper = tick_upper;
-        user_position.liquidity = liquidity;
-        user_position.fee_growth_checkpoint_a = 0;
-        user_position.fee_growth_checkpoint_b = 0;
 
-        // If tick is active update pool liquidity
-        if pool.current_tick >= tick_lower && pool.current_tick < tick_upper {
-            pool.liquidity = pool.liquidity.checked_add(liquidity).unwrap();
+        if realm.min_proposal_weight > 0 {
+            let stake = ctx
+                .accounts
+                .stake_vault
+                .as_ref()
+                .map_or(0, |stake_vault| stake_vault.amount);
+            require!(
+                stake >= realm.min_proposal_weight,
+                CustomError::InsufficientProposalWeight
+            );
         }
 
-        msg!(
-            "Position opened: {} liquidity between ticks {} - {}",
-            liquidity,
-            tick_lower,
-            tick_upper
-        );
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.id = proposal_id;
+        proposal.profile = ctx.accounts.profile.key();
+        proposal.title = title;
+        proposal.description = description;
+        proposal.created_at = clock.unix_timestamp;
+        proposal.deadline = deadline;
+        proposal.yes_votes = 0;
+        proposal.no_votes = 0;
+        proposal.yes_weight = 0;
+        proposal.no_weight = 0;
+        proposal.bump = ctx.bumps.proposal;
+        proposal.executed = false;
+        proposal.status = ProposalStatus::Voting;
+        proposal.vote_records = 0;
+        // The rules in force at creation decide the outcome, whatever the realm says later
+        proposal.quorum_weight = realm.quorum.weight(ctx.accounts.registry.active_weight);
+        proposal.approval_threshold_bps = realm.approval_threshold_bps;
+        proposal.instructions = instructions;
+
+        let profile = &mut ctx.accounts.profile;
+        profile.proposal_count = profile
+            .proposal_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
 
         Ok(())
     }
 
-    pub fn decrease_liquidity(
-        ctx: Context<DecreaseLiquidity>,
-        liquidity_to_remove: u128,
-    ) -> Result<()> {
-        let pool = &mut ctx.accounts.pool_clmm;
-        let tick_lower = &mut ctx.accounts.tick_lower;
-        let tick_upper = &mut ctx.accounts.tick_upper;
-        let user_position = &mut ctx.accounts.user_position;
+    pub fn vote_on_proposal(ctx: Context<VoteOnProposal>, choice: VoteChoice) -> Result<()> {
+        SessionKey::authorize(
+            ctx.accounts.session.as_ref(),
+            &ctx.accounts.authority.key(),
+            &ctx.accounts.signer.key(),
+            SessionKey::SCOPE_VOTE,
+        )?;
+
+        let clock = Clock::get()?;
+        let proposal = &mut ctx.accounts.proposal;
 
-        // Safety checks
-        require!(liquidity_to_remove > 0, CustomError::ZeroLiquidity);
         require!(
-            liquidity_to_remove <= user_position.liquidity,
-            CustomError::InvalidLiquidityAmount
+            clock.unix_timestamp < proposal.deadline,
+            CustomError::ProposalExpired
         );
 
-        // Update tick liquidity_net
-        tick_lower.liquidity_net = tick_lower
-            .liquidity_net
-            .checked_sub(liquidity_to_remove as i128)
-            .unwrap();
-        tick_upper.liquidity_net = tick_upper
-            .liquidity_net
-            .checked_add(liquidity_to_remove as i128)
-            .unwrap();
-
-        // Update pool liquidity if position is currently active
-        if pool.current_tick >= tick_lower.tick_index && pool.current_tick < tick_upper.tick_index {
-            pool.liquidity = pool.liquidity.checked_sub(liquidity_to_remove).unwrap()
-        }
+        // A validator migrated to a new authority after the proposal opened may already have voted under its old key
+        require!(
+            ctx.accounts.validator.migrated_at <= proposal.created_at,
+            CustomError::ValidatorMigrated
+        );
 
-        // Placeholder right now (Dummy token A & B to return)
-        let amount_a = (liquidity_to_remove / 1000) as u64;
-        let amount_b = (liquidity_to_remove / 1000) as u64;
+        // Voting power is the stake held since before the proposal opened, so stake
+        // moved in (or re-staked) after creation cannot be counted
+        let stake_vault = &ctx.accounts.stake_vault;
+        require!(
+            stake_vault.weight_since < proposal.created_at,
+            CustomError::StakeTooRecent
+        );
+        let weight = ctx
+            .accounts
+            .uptime
+            .weigh(stake_vault.amount, clock.unix_timestamp);
+        require!(weight > 0, CustomError::NoVotingPower);
+
+        proposal.tally(choice, weight)?;
+        proposal.vote_records = proposal
+            .vote_records
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
 
-        // CPI transfer token A back to the user
-        let pool_key = pool.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"signer", pool_key.as_ref(), &[pool.signer_bump]]];
+        let vote_record = &mut ctx.accounts.vote_record;
+        vote_record.proposal = proposal.key();
+        vote_record.validator = ctx.accounts.validator.key();
+        vote_record.stake_vault = stake_vault.key();
+        vote_record.weight = weight;
+        vote_record.choice = choice;
+        vote_record.timestamp = clock.unix_timestamp;
+        vote_record.bump = ctx.bumps.vote_record;
+        vote_record.payer = ctx.accounts.signer.key();
 
-        let cpi_ctx_a = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_a.to_account_info(),
-                to: ctx.accounts.user_token_a.to_account_info(),
-                authority: ctx.accounts.pool_signer.to_account_info(),
-            },
-            signer_seeds,
-        );
-        token::transfer(cpi_ctx_a, amount_a)?;
+        Ok(())
+    }
 
-        // Cpi Transfer token B back to user
-        let cpi_ctx_b = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_b.to_account_info(),
-                to: ctx.accounts.user_token_b.to_account_info(),
-                authority: ctx.accounts.pool_signer.to_account_info(),
-            },
-            signer_seeds,
-        );
-        token::transfer(cpi_ctx_b, amount_b)?;
+    /// Moves an existing vote's weight to another choice while voting is open
+    pub fn change_vote(ctx: Context<ChangeVote>, choice: VoteChoice) -> Result<()> {
+        SessionKey::authorize(
+            ctx.accounts.session.as_ref(),
+            &ctx.accounts.authority.key(),
+            &ctx.accounts.signer.key(),
+            SessionKey::SCOPE_VOTE,
+        )?;
 
-        // Update users position
-        user_position.liquidity = user_position
-            .liquidity
-            .checked_sub(liquidity_to_remove)
-            .unwrap();
+        let now = Clock::get()?.unix_timestamp;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(now < proposal.deadline, CustomError::ProposalExpired);
 
-        msg!(
-            "Liquidity removed: {} → A: {}, B: {} | Remaining: {}",
-            liquidity_to_remove,
-            amount_a,
-            amount_b,
-            user_position.liquidity
-        );
+        let vote_record = &mut ctx.accounts.vote_record;
+        proposal.untally(vote_record.choice, vote_record.weight);
+        proposal.tally(choice, vote_record.weight)?;
+        vote_record.choice = choice;
+        vote_record.timestamp = now;
 
         Ok(())
     }
 
-    pub fn collect_fees(ctx: Context<CollectFees>) -> Result<()> {
-        let pool = &ctx.accounts.pool_clmm;
-        let user_position = &mut ctx.accounts.user_position;
+    /// Takes a vote back while voting is open; the record's rent goes back to whoever paid it
+    pub fn withdraw_vote(ctx: Context<WithdrawVote>) -> Result<()> {
+        SessionKey::authorize(
+            ctx.accounts.session.as_ref(),
+            &ctx.accounts.authority.key(),
+            &ctx.accounts.signer.key(),
+            SessionKey::SCOPE_VOTE,
+        )?;
 
-        // Assuming 10 tokens of A and B
-        let earned_a: u64 = 10;
-        let earned_b: u64 = 10;
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            Clock::get()?.unix_timestamp < proposal.deadline,
+            CustomError::ProposalExpired
+        );
 
-        // PDA Signer setup
-        let pool_key = pool.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"signer", pool_key.as_ref(), &[pool.signer_bump]]];
+        let vote_record = &ctx.accounts.vote_record;
+        proposal.untally(vote_record.choice, vote_record.weight);
+        proposal.vote_records = proposal.vote_records.saturating_sub(1);
 
-        // Transfer A
-        let cpi_ctx_a = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_a.to_account_info(),
-                to: ctx.accounts.user_token_a.to_account_info(),
-                authority: ctx.accounts.pool_signer.to_account_info(),
-            },
-            signer_seeds,
-        );
-        token::transfer(cpi_ctx_a, earned_a)?;
+        Ok(())
+    }
 
-        // Transfer B
-        let cpi_ctx_b = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_b.to_account_info(),
-                to: ctx.accounts.user_token_b.to_account_info(),
-                authority: ctx.accounts.pool_signer.to_account_info(),
-            },
-            signer_seeds,
+    /// Permissionless once the deadline passes: records whether the proposal
+    /// passed, was rejected or missed quorum under the rules it was created with
+    pub fn finalize_proposal(ctx: Context<FinalizeProposal>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            Clock::get()?.unix_timestamp >= proposal.deadline,
+            CustomError::VotingNotEnded
         );
-        token::transfer(cpi_ctx_b, earned_b)?;
-
-        // Update fee checkpoints (placeholder — real values will come from pool)
-        user_position.fee_growth_checkpoint_a = pool.fee_growth_global_a;
-        user_position.fee_growth_checkpoint_b = pool.fee_growth_global_b;
 
-        msg!("Fees collected: {} A and {} B", earned_a, earned_b);
+        proposal.status = proposal.outcome();
 
+        msg!(
+            "Proposal {} finalized as {:?}",
+            proposal.key(),
+            proposal.status
+        );
         Ok(())
     }
 
-    pub fn swap_clmm(
-        ctx: Context<SwapClmm>,
-        amount_in: u64,
-        min_amount_out: u64,
-        a_to_b: bool,
-    ) -> Result<()> {
-        let pool = &mut ctx.accounts.pool_clmm;
+    /// The creator can withdraw a proposal nobody has voted on yet
+    pub fn cancel_proposal(ctx: Context<CancelProposal>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        require!(proposal.vote_records == 0, CustomError::ProposalHasVotes);
+        proposal.status = ProposalStatus::Cancelled;
+        Ok(())
+    }
 
-        // Fee handlding
-        let fee_rate = pool.fee_rate as u64;
-        let fee_amount = amount_in
-            .checked_mul(fee_rate)
-            .unwrap()
-            .checked_div(10_000)
-            .unwrap();
+    /// Permissionless after finalization; the rent goes back to whoever paid it
+    pub fn close_vote_record(ctx: Context<CloseVoteRecord>) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        proposal.vote_records = proposal.vote_records.saturating_sub(1);
+        Ok(())
+    }
 
-        let amount_in_after_fee = amount_in.checked_sub(fee_amount).unwrap();
-        let amount_out = amount_in_after_fee; // 1:1 for now
+    /// Reclaims the proposal's rent once it is final and every vote record is closed
+    pub fn close_proposal(ctx: Context<CloseProposal>) -> Result<()> {
+        let proposal = &ctx.accounts.proposal;
+        // A passed proposal has to run before it can go
+        require!(
+            proposal.status != ProposalStatus::Passed
+                || proposal.executed
+                || proposal.instructions.is_empty(),
+            CustomError::ProposalNotExecuted
+        );
 
-        // Fee growth update
-        if pool.liquidity > 0 {
-            let fee_growth = ((fee_amount as u128) << 64) / pool.liquidity;
-            if a_to_b {
-                pool.fee_growth_global_a =
-                    pool.fee_growth_global_a.checked_add(fee_growth).unwrap();
-            } else {
-                pool.fee_growth_global_b =
-                    pool.fee_growth_global_b.checked_add(fee_growth).unwrap();
-            }
+        let legacy = proposal.profile != ctx.accounts.profile.key();
+        let profile = &mut ctx.accounts.profile;
+        profile.proposal_count = profile.proposal_count.saturating_sub(1);
+        if legacy {
+            profile.pending_migrations = profile.pending_migrations.saturating_sub(1);
         }
-        // --- Token Transfer In: From User → Vault ---
-        let cpi_ctx_in = CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.user_source_token.to_account_info(),
-                to: ctx.accounts.vault_source.to_account_info(),
-                authority: ctx.accounts.user.to_account_info(),
-            },
+        Ok(())
+    }
+
+    /// Runs the instructions of a proposal finalized as Passed. `remaining_accounts`
+    /// must hold every account and program the instructions reference
+    pub fn execute_proposal<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteProposal<'info>>,
+    ) -> Result<()> {
+        let proposal = &mut ctx.accounts.proposal;
+        require!(
+            proposal.status == ProposalStatus::Passed,
+            CustomError::ProposalNotPassed
+        );
+        // Already part of `outcome`, checked again so no path to Passed can skip it
+        require!(proposal.reached_quorum(), CustomError::QuorumNotReached);
+        require!(
+            !proposal.instructions.is_empty(),
+            CustomError::NothingToExecute
         );
-        token::transfer(cpi_ctx_in, amount_in)?;
 
-        // --- Token Transfer Out: From Vault → User (Signer PDA) ---
-        let pool_key = pool.key();
-        let signer_seeds: &[&[&[u8]]] =
-            &[&[b"signer", pool_key.as_ref(), &[ctx.bumps.pool_signer]]];
+        // Marked first so a CPI back into the program can't replay it
+        proposal.executed = true;
 
-        let cpi_ctx_out = CpiContext::new_with_signer(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx.accounts.vault_destination.to_account_info(),
-                to: ctx.accounts.user_destination_token.to_account_info(),
-                authority: ctx.accounts.pool_signer.to_account_info(),
-            },
-            signer_seeds,
-        );
-        token::transfer(cpi_ctx_out, amount_out)?;
+        let bump = ctx.bumps.governance_treasury;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"governance-treasury", &[bump]]];
 
-        // --- Validation ---
-        require!(amount_out >= min_amount_out, CustomError::SlippageExceeded);
+        let mut account_infos = ctx.remaining_accounts.to_vec();
+        account_infos.push(ctx.accounts.governance_treasury.to_account_info());
+
+        for ix in ctx.accounts.proposal.instructions.iter() {
+            let ix = anchor_lang::solana_program::instruction::Instruction {
+                program_id: ix.program_id,
+                accounts: ix
+                    .accounts
+                    .iter()
+                    .map(
+                        |acc| anchor_lang::solana_program::instruction::AccountMeta {
+                            pubkey: acc.pubkey,
+                            is_signer: acc.is_signer,
+                            is_writable: acc.is_writable,
+                        },
+                    )
+                    .collect(),
+                data: ix.data.clone(),
+            };
+            anchor_lang::solana_program::program::invoke_signed(&ix, &account_infos, signer_seeds)?;
+        }
 
         msg!(
-            "Swapped {} (A→B: {}) → {}, fee: {}",
-            amount_in,
-            a_to_b,
-            amount_out,
-            fee_amount
+            "Executed {} instructions of proposal {}",
+            ctx.accounts.proposal.instructions.len(),
+            ctx.accounts.proposal.key()
         );
-
         Ok(())
     }
 
-    pub fn close_position(
-        ctx: Context<ClosePosition>,
-        tick_lower: i32,
-        tick_upper: i32,
+    pub fn stake_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, StakeTokens<'info>>,
+        amount: u64,
     ) -> Result<()> {
-        let user_position = &ctx.accounts.user_position;
+        SessionKey::authorize(
+            ctx.accounts.session.as_ref(),
+            &ctx.accounts.user.key(),
+            &ctx.accounts.signer.key(),
+            SessionKey::SCOPE_STAKE,
+        )?;
 
-        // Only allow closing if all liquidity is removed
-        require!(
-            user_position.liquidity == 0,
-            CustomError::PositionStillHasLiquidity
+        let stake_vault = &mut ctx.accounts.stake_vault;
+
+        require!(stake_vault.amount == 0, CustomError::AlreadyStaked);
+        require!(amount > 0, CustomError::ZeroStake);
+
+        let now = Clock::get()?.unix_timestamp;
+
+        // Transfer tokens from user ATA to vault ATA. A session key signing here
+        // must also be an SPL delegate of the user's ATA
+        let vault_before = ctx.accounts.vault_ata.amount;
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_ata.to_account_info(),
+                mint: ctx.accounts.stake_mint.to_account_info(),
+                to: ctx.accounts.vault_ata.to_account_info(),
+                authority: ctx.accounts.signer.to_account_info(),
+            },
         );
+        transfer_checked_with_hook(
+            cpi_ctx.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount,
+            ctx.accounts.stake_mint.decimals,
+        )?;
+
+        // Transfer-fee mints deliver less than `amount`; only what arrived is staked
+        ctx.accounts.vault_ata.reload()?;
+        let amount = ctx
+            .accounts
+            .vault_ata
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(CustomError::MathOverflow)?;
+
+        // Record staking data
+        stake_vault.owner = ctx.accounts.user.key();
+        stake_vault.profile = ctx.accounts.profile.key();
+        stake_vault.vault = ctx.accounts.vault_ata.key();
+        stake_vault.amount = amount;
+        stake_vault.start_stake_time = now;
+        stake_vault.weight_since = now;
+        stake_vault.reward_collected = 0;
+        stake_vault.pool = ctx.accounts.pool.key();
+        stake_vault.bump = ctx.bumps.stake_vault;
+
+        // Update pool stats
+        let pool = &mut ctx.accounts.pool;
+        pool.total_staked = pool
+            .total_staked
+            .checked_add(amount)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.total_weight = registry
+            .total_weight
+            .checked_add(amount)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let profile = &mut ctx.accounts.profile;
+        profile.active_stakes = profile
+            .active_stakes
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
 
         msg!(
-            "Position closed for user: {} | tick range: [{} - {}]",
-            ctx.accounts.owner.key(),
-            tick_lower,
-            tick_upper
+            "Staked {} tokens at time {} into pool {}",
+            amount,
+            now,
+            pool.id
         );
-
         Ok(())
     }
 
-    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
-        let pool = &mut ctx.accounts.pool_clmm;
+    pub fn unstake_tokens<'info>(
+        ctx: Context<'_, '_, '_, 'info, UnstakeTokens<'info>>,
+    ) -> Result<()> {
+        // An operator's stake backs its validators and must stay slashable
+        require!(
+            ctx.accounts.profile.validator_count == 0,
+            CustomError::OperatorStakeBonded
+        );
 
-        let fee_a = pool.protocol_fee_a;
-        let fee_b = pool.protocol_fee_b;
+        let stake_vault = &mut ctx.accounts.stake_vault;
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &ctx.accounts.pool;
 
-        require!(fee_a > 0 || fee_b > 0, CustomError::NoProtocolFeesToCollect);
+        require!(
+            now >= stake_vault.start_stake_time + pool.lock_period as i64,
+            CustomError::StakeLocked
+        );
 
-        let pool_key = pool.key();
-        let signer_seeds: &[&[&[u8]]] = &[&[b"signer", pool_key.as_ref(), &[pool.signer_bump]]];
+        let amount = stake_vault.amount;
+        require!(amount > 0, CustomError::ZeroStake);
 
-        // Transfer token A protocol fee
-        if fee_a > 0 {
-            let cpi_ctx_a = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault_a.to_account_info(),
-                    to: ctx.accounts.protocol_fee_recipient_a.to_account_info(),
-                    authority: ctx.accounts.pool_signer.to_account_info(),
-                },
-                signer_seeds,
-            );
-            token::transfer(cpi_ctx_a, fee_a)?;
-        }
+        // Fix temporary borrow issue
+        let user_key = ctx.accounts.user.key();
+        let bump = ctx.bumps.stake_vault;
+        let signer_seeds: &[&[u8]] = &[b"stake-vault", user_key.as_ref(), &[bump]];
+        let signer: &[&[&[u8]]] = &[signer_seeds];
 
-        // Transfer token B protocol fee
-        if fee_b > 0 {
-            let cpi_ctx_b = CpiContext::new_with_signer(
-                ctx.accounts.token_program.to_account_info(),
-                Transfer {
-                    from: ctx.accounts.vault_b.to_account_info(),
-                    to: ctx.accounts.protocol_fee_recipient_b.to_account_info(),
-                    authority: ctx.accounts.pool_signer.to_account_info(),
-                },
-                signer_seeds,
-            );
-            token::transfer(cpi_ctx_b, fee_b)?;
-        }
+        // Use already borrowed `stake_vault` here
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_ata.to_account_info(),
+                mint: ctx.accounts.stake_mint.to_account_info(),
+                to: ctx.accounts.user_ata.to_account_info(),
+                authority: stake_vault.to_account_info(),
+            },
+            signer,
+        );
 
-        // Reset protocol fees after collection
-        pool.protocol_fee_a = 0;
-        pool.protocol_fee_b = 0;
+        transfer_checked_with_hook(
+            cpi_ctx.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount,
+            ctx.accounts.stake_mint.decimals,
+        )?;
 
-        msg!("Protocol fees collected: {} A, {} B", fee_a, fee_b);
+        stake_vault.amount = 0;
+        stake_vault.start_stake_time = 0;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.total_staked = pool
+            .total_staked
+            .checked_sub(amount)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.total_weight = registry.total_weight.saturating_sub(amount);
+
+        let profile = &mut ctx.accounts.profile;
+        profile.active_stakes = profile.active_stakes.saturating_sub(1);
 
+        msg!("Unstaked {} tokens at time {}", amount, now);
         Ok(())
     }
 
-    //     pub fn handle_buy_nft(
-    //         ctx: Context<BuyNft>,
-    //     ) -> Result<()> {
-    //         let ata = associated_token::get_associated_token_address(
-    //             &ctx.accounts.buyer_authority.key(),
-    //             &ctx.accounts.nft_mint.key());
-    //         require!(ata == ctx.accounts.buyer_nft_account.key(), CustomError::AlreadyExecuted);
-
-    //         if ctx.accounts.buyer_nft_account.data_len() == 0{
-    //             //create buyer nft token account
-    //             associated_token::create(CpiContext::new(ctx.accounts.associated_token_program.to_account_info(),
-    //             associated_token:: Create{
-    //                     payer: ctx.accounts.buyer_authority.to_account_info(),
-    //                     associated_token: ctx.accounts.buyer_nft_account.to_account_info(),
-    //                     authority: ctx.accounts.buyer_authority.to_account_info(),
-    //                     mint: ctx.accounts.nft_mint.to_account_info(),
-    //                     system_program: ctx.accounts.system_program.to_account_info(),
-    //                     token_program: ctx.accounts.token_program.to_account_info(),
-    //                 }
-    //             ))
-    //         } else {
+    pub fn claim_reward(ctx: Context<ClaimReward>) -> Result<()> {
+        SessionKey::authorize(
+            ctx.accounts.session.as_ref(),
+            &ctx.accounts.user.key(),
+            &ctx.accounts.signer.key(),
+            SessionKey::SCOPE_CLAIM_REWARD,
+        )?;
 
-    //             Ok(())
-    //         }
-    // }
-}
+        let stake_vault = &mut ctx.accounts.stake_vault;
+        let now = Clock::get()?.unix_timestamp;
 
-// ----------------- CONTEXT STRUCTS ---------------------
+        let elapsed = now
+            .checked_sub(stake_vault.start_stake_time)
+            .ok_or(CustomError::TimeCalculationFailed)?;
 
-// #[derive(Accounts)]
-// pub struct BuyNft<'info> {
-//     #[account(mut)]
-//     pub buyer_authority: Signer<'info>,
-//     #[account(mut)]
-//     pub buyer_nft_account: AccountInfo<'info>,
-//     pub nft_mint: Account<'info, Mint>,
-//     pub system_program: Program<'info, System>,
-//     pub token_program: Program<'info, Token>,
-//     pub associated_token_program: Program<'info, AssociatedToken>,
-// }
-#[derive(Accounts)]
-#[instruction(name: String)]
-pub struct InitProfile<'info> {
-    #[account(
-        init,
-        seeds = [b"profile", authority.key().as_ref()],
-        bump,
-        payer = authority,
-        space = UserProfile::LEN
-    )]
-    pub profile: Account<'info, UserProfile>, // The Account type will check that the owner of the account being loaded is actually owned by the program
+        let reward_rate = ctx.accounts.pool.reward_per_second;
+        let total_reward = (elapsed as u64)
+            .checked_mul(reward_rate)
+            .ok_or(CustomError::MathOverflow)?;
+        let pending = total_reward.saturating_sub(stake_vault.reward_collected);
 
-    #[account(mut)]
-    pub authority: Signer<'info>,
-    pub system_program: Program<'info, System>,
-}
+        require!(pending > 0, CustomError::NoRewardAvailable);
+        ctx.accounts.pool.spend_rewards(pending)?;
+        let reward_mint = ctx.accounts.reward_mint.key();
+        ctx.accounts
+            .mint_config
+            .record_reward(reward_mint, pending, now)?;
 
-#[derive(Accounts)]
-pub struct CreateMint<'info> {
-    #[account(
-        init_if_needed,
-        payer = payer,
-        mint::decimals = 9,
-        mint::authority = mint_authority,
-        mint::freeze_authority = mint_authority,
-        seeds = [b"global-mint"],
-        bump
-    )]
-    pub mint: Account<'info, Mint>,
+        let bump = ctx.bumps.mint_authority;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
 
-    /// CHECK: PDA mint authority, validated via seed constraints
-    #[account(seeds = [b"mint-authority"], bump)]
-    pub mint_authority: UncheckedAccount<'info>, // UncheckedAccount is an alias for AccountInfo. This does not check for ownership, so care must be taken as it will accept arbitrary accounts.
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.reward_mint.to_account_info(),
+                to: ctx.accounts.user_reward_ata.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        token_interface::mint_to(cpi_ctx, pending)?;
+        stake_vault.reward_collected = stake_vault
+            .reward_collected
+            .checked_add(pending)
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!(
+            "Minted {} tokens as reward to user {} from pool {}",
+            pending,
+            ctx.accounts.user.key(),
+            ctx.accounts.pool.id
+        );
+
+        Ok(())
+    }
+
+    pub fn init_staking_pool(
+        ctx: Context<InitStakingPool>,
+        id: u64,
+        name: String,
+        reward_per_second: u64,
+        lock_period: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        pool.id = id;
+        pool.name = name;
+        pool.authority = ctx.accounts.authority.key();
+        pool.stake_mint = ctx.accounts.stake_mint.key();
+        pool.reward_mint = ctx.accounts.reward_mint.key();
+        pool.reward_per_second = reward_per_second;
+        pool.total_staked = 0;
+        pool.lock_period = lock_period;
+        pool.bump = ctx.bumps.pool;
+        pool.reward_vault = ctx.accounts.reward_vault.key();
+        pool.reward_vault_authority_bump = ctx.bumps.reward_vault_authority;
+        pool.last_reward_time = Clock::get()?.unix_timestamp;
+
+        Ok(())
+    }
+
+    pub fn refill_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, RefillPool<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        // Transfer tokens from admin_ata -> reward_vault
+        let vault_before = ctx.accounts.reward_vault.amount;
+        transfer_checked_with_hook(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.admin_ata.to_account_info(),
+                    mint: ctx.accounts.reward_mint.to_account_info(),
+                    to: ctx.accounts.reward_vault.to_account_info(),
+                    authority: ctx.accounts.admin.to_account_info(),
+                },
+            )
+            .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount,
+            ctx.accounts.reward_mint.decimals,
+        )?;
+
+        // Track internal reward balance, net of any transfer fee
+        ctx.accounts.reward_vault.reload()?;
+        let amount = ctx
+            .accounts
+            .reward_vault
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(CustomError::MathOverflow)?;
+        let pool = &mut ctx.accounts.pool;
+        pool.reward_balance = pool.reward_balance.saturating_add(amount);
+
+        msg!(
+            "Refilled pool {} with {} tokens into reward vault",
+            pool.id,
+            amount
+        );
+
+        Ok(())
+    }
+
+    pub fn update_pool_config(
+        ctx: Context<UpdatePoolConfig>,
+        new_rate: u64,
+        new_lock_period: u64,
+        pause: bool,
+    ) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts
+            .staking_pool
+            .update_config(new_rate, new_lock_period, pause, now);
+
+        msg!(
+            "Updated pool config → rate: {}, lock: {}, paused: {}",
+            new_rate,
+            new_lock_period,
+            pause
+        );
+
+        Ok(())
+    }
+
+    /// Opens a validator to delegations from `pool` (on first call) and sets the cut
+    /// it keeps from delegation rewards. Rewards accrued so far use the old rate
+    pub fn set_validator_commission(
+        ctx: Context<SetValidatorCommission>,
+        commission_bps: u16,
+    ) -> Result<()> {
+        require!(
+            commission_bps <= ValidatorInfo::MAX_COMMISSION_BPS,
+            CustomError::InvalidCommission
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue_delegation_rewards(now);
+
+        let validator = &mut ctx.accounts.validator;
+        if validator.pool == Pubkey::default() {
+            validator.pool = pool.key();
+            validator.reward_per_token_paid = pool.acc_reward_per_token;
+        }
+        validator.accrue_rewards(pool, ctx.accounts.uptime.score_bps(now));
+        validator.commission_bps = commission_bps;
+
+        msg!(
+            "Validator {} commission set to {} bps in pool {}",
+            validator.id,
+            commission_bps,
+            pool.id
+        );
+        Ok(())
+    }
+
+    pub fn delegate<'info>(
+        ctx: Context<'_, '_, '_, 'info, Delegate<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        require!(amount > 0, CustomError::ZeroStake);
+
+        let vault_before = ctx.accounts.validator_vault.amount;
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.delegator_ata.to_account_info(),
+                mint: ctx.accounts.stake_mint.to_account_info(),
+                to: ctx.accounts.validator_vault.to_account_info(),
+                authority: ctx.accounts.delegator.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            cpi_ctx.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount,
+            ctx.accounts.stake_mint.decimals,
+        )?;
+
+        // Only what arrived is delegated
+        ctx.accounts.validator_vault.reload()?;
+        let amount = ctx
+            .accounts
+            .validator_vault
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue_delegation_rewards(now);
+
+        let validator = &mut ctx.accounts.validator;
+        validator.accrue_rewards(pool, ctx.accounts.uptime.score_bps(now));
+
+        let shares = validator.shares_for(amount);
+        require!(shares > 0, CustomError::ZeroStake);
+
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.delegator = ctx.accounts.delegator.key();
+        delegation.validator = validator.key();
+        delegation.bump = ctx.bumps.delegation;
+        let total = delegation
+            .shares
+            .checked_add(shares)
+            .ok_or(CustomError::MathOverflow)?;
+        delegation.set_shares(total, validator);
+
+        validator.delegated_stake = validator
+            .delegated_stake
+            .checked_add(amount)
+            .ok_or(CustomError::MathOverflow)?;
+        validator.total_shares = validator
+            .total_shares
+            .checked_add(shares)
+            .ok_or(CustomError::MathOverflow)?;
+        pool.delegated_stake = pool
+            .delegated_stake
+            .checked_add(amount)
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!(
+            "Delegated {} tokens ({} shares) to validator {}",
+            amount,
+            shares,
+            validator.key()
+        );
+        Ok(())
+    }
+
+    pub fn undelegate<'info>(
+        ctx: Context<'_, '_, '_, 'info, Undelegate<'info>>,
+        shares: u64,
+    ) -> Result<()> {
+        require!(
+            shares > 0 && shares <= ctx.accounts.delegation.shares,
+            CustomError::InsufficientShares
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.validator.require_unbonded(now)?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue_delegation_rewards(now);
+
+        let validator = &mut ctx.accounts.validator;
+        validator.accrue_rewards(pool, ctx.accounts.uptime.score_bps(now));
+
+        // Shares are redeemed at the current rate, so slashes are shared pro rata
+        let amount = validator.stake_for(shares);
+
+        let delegation = &mut ctx.accounts.delegation;
+        let remaining = delegation.shares - shares;
+        delegation.set_shares(remaining, validator);
+
+        validator.total_shares -= shares;
+        validator.delegated_stake = validator
+            .delegated_stake
+            .checked_sub(amount)
+            .ok_or(CustomError::MathOverflow)?;
+        pool.delegated_stake = pool.delegated_stake.saturating_sub(amount);
+
+        if amount > 0 {
+            let authority = validator.authority;
+            let id_bytes = validator.id.to_le_bytes();
+            let signer_seeds: &[&[&[u8]]] = &[&[
+                b"validator",
+                authority.as_ref(),
+                &id_bytes,
+                &[validator.bump],
+            ]];
+
+            transfer_checked_with_hook(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.validator_vault.to_account_info(),
+                        mint: ctx.accounts.stake_mint.to_account_info(),
+                        to: ctx.accounts.delegator_ata.to_account_info(),
+                        authority: ctx.accounts.validator.to_account_info(),
+                    },
+                    signer_seeds,
+                )
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+                amount,
+                ctx.accounts.stake_mint.decimals,
+            )?;
+        }
+
+        msg!(
+            "Undelegated {} shares ({} tokens) from validator {}",
+            shares,
+            amount,
+            ctx.accounts.validator.key()
+        );
+        Ok(())
+    }
+
+    /// Moves delegated stake between two validators of the same pool without
+    /// passing through the delegator's wallet
+    pub fn redelegate<'info>(
+        ctx: Context<'_, '_, '_, 'info, Redelegate<'info>>,
+        shares: u64,
+    ) -> Result<()> {
+        require!(
+            shares > 0 && shares <= ctx.accounts.source_delegation.shares,
+            CustomError::InsufficientShares
+        );
+        let now = Clock::get()?.unix_timestamp;
+        ctx.accounts.source_validator.require_unbonded(now)?;
+
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue_delegation_rewards(now);
+
+        let source = &mut ctx.accounts.source_validator;
+        source.accrue_rewards(pool, ctx.accounts.source_uptime.score_bps(now));
+        let amount = source.stake_for(shares);
+
+        let source_delegation = &mut ctx.accounts.source_delegation;
+        let remaining = source_delegation.shares - shares;
+        source_delegation.set_shares(remaining, source);
+        source.total_shares -= shares;
+        source.delegated_stake = source
+            .delegated_stake
+            .checked_sub(amount)
+            .ok_or(CustomError::MathOverflow)?;
+
+        if amount > 0 {
+            let authority = source.authority;
+            let id_bytes = source.id.to_le_bytes();
+            let signer_seeds: &[&[&[u8]]] =
+                &[&[b"validator", authority.as_ref(), &id_bytes, &[source.bump]]];
+
+            let vault_before = ctx.accounts.destination_vault.amount;
+            transfer_checked_with_hook(
+                CpiContext::new_with_signer(
+                    ctx.accounts.token_program.to_account_info(),
+                    TransferChecked {
+                        from: ctx.accounts.source_vault.to_account_info(),
+                        mint: ctx.accounts.stake_mint.to_account_info(),
+                        to: ctx.accounts.destination_vault.to_account_info(),
+                        authority: ctx.accounts.source_validator.to_account_info(),
+                    },
+                    signer_seeds,
+                )
+                .with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+                amount,
+                ctx.accounts.stake_mint.decimals,
+            )?;
+
+            ctx.accounts.destination_vault.reload()?;
+            let received = ctx
+                .accounts
+                .destination_vault
+                .amount
+                .checked_sub(vault_before)
+                .ok_or(CustomError::MathOverflow)?;
+
+            let destination = &mut ctx.accounts.destination_validator;
+            destination.accrue_rewards(
+                &ctx.accounts.pool,
+                ctx.accounts.destination_uptime.score_bps(now),
+            );
+
+            let new_shares = destination.shares_for(received);
+            let destination_delegation = &mut ctx.accounts.destination_delegation;
+            destination_delegation.delegator = ctx.accounts.delegator.key();
+            destination_delegation.validator = destination.key();
+            destination_delegation.bump = ctx.bumps.destination_delegation;
+            let total = destination_delegation
+                .shares
+                .checked_add(new_shares)
+                .ok_or(CustomError::MathOverflow)?;
+            destination_delegation.set_shares(total, destination);
+
+            destination.delegated_stake = destination
+                .delegated_stake
+                .checked_add(received)
+                .ok_or(CustomError::MathOverflow)?;
+            destination.total_shares = destination
+                .total_shares
+                .checked_add(new_shares)
+                .ok_or(CustomError::MathOverflow)?;
+
+            // Any transfer fee leaves the pool's delegated total
+            let pool = &mut ctx.accounts.pool;
+            pool.delegated_stake = pool.delegated_stake.saturating_sub(amount - received);
+        }
+
+        msg!(
+            "Redelegated {} shares ({} tokens) from {} to {}",
+            shares,
+            amount,
+            ctx.accounts.source_validator.key(),
+            ctx.accounts.destination_validator.key()
+        );
+        Ok(())
+    }
+
+    pub fn claim_delegation_rewards(ctx: Context<ClaimDelegationRewards>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue_delegation_rewards(now);
+
+        let validator = &mut ctx.accounts.validator;
+        validator.accrue_rewards(pool, ctx.accounts.uptime.score_bps(now));
+
+        let delegation = &mut ctx.accounts.delegation;
+        delegation.settle(validator);
+        let pending = delegation.pending_reward;
+        require!(pending > 0, CustomError::NoRewardAvailable);
+        delegation.pending_reward = 0;
+
+        ctx.accounts.pool.spend_rewards(pending)?;
+        let reward_mint = ctx.accounts.reward_mint.key();
+        ctx.accounts
+            .mint_config
+            .record_reward(reward_mint, pending, now)?;
+
+        let bump = ctx.bumps.mint_authority;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.reward_mint.to_account_info(),
+                to: ctx.accounts.delegator_reward_ata.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        token_interface::mint_to(cpi_ctx, pending)?;
+
+        msg!(
+            "Minted {} delegation rewards to {}",
+            pending,
+            ctx.accounts.delegator.key()
+        );
+        Ok(())
+    }
+
+    pub fn claim_commission(ctx: Context<ClaimCommission>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let pool = &mut ctx.accounts.pool;
+        pool.accrue_delegation_rewards(now);
+
+        let validator = &mut ctx.accounts.validator;
+        validator.accrue_rewards(pool, ctx.accounts.uptime.score_bps(now));
+        let pending = validator.pending_commission;
+        require!(pending > 0, CustomError::NoRewardAvailable);
+        validator.pending_commission = 0;
+
+        ctx.accounts.pool.spend_rewards(pending)?;
+        let reward_mint = ctx.accounts.reward_mint.key();
+        ctx.accounts
+            .mint_config
+            .record_reward(reward_mint, pending, now)?;
+
+        let bump = ctx.bumps.mint_authority;
+        let signer_seeds: &[&[&[u8]]] = &[&[b"mint-authority", &[bump]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.reward_mint.to_account_info(),
+                to: ctx.accounts.authority_reward_ata.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+
+        token_interface::mint_to(cpi_ctx, pending)?;
+
+        msg!(
+            "Minted {} commission to validator {}",
+            pending,
+            ctx.accounts.validator.key()
+        );
+        Ok(())
+    }
+
+    pub fn init_validator_config(
+        ctx: Context<InitValidatorConfig>,
+        jail_duration: i64,
+        unbonding_period: i64,
+        slash_destination: Pubkey,
+        epoch_length: i64,
+        max_missed_epochs: u64,
+    ) -> Result<()> {
+        require!(
+            jail_duration >= 0 && unbonding_period >= 0,
+            CustomError::InvalidValidatorConfig
+        );
+        require!(
+            epoch_length > 0 && max_missed_epochs > 0,
+            CustomError::InvalidValidatorConfig
+        );
+
+        let config = &mut ctx.accounts.validator_config;
+        config.admin = ctx.accounts.admin.key();
+        config.jail_duration = jail_duration;
+        config.unbonding_period = unbonding_period;
+        config.slash_destination = slash_destination;
+        config.epoch_length = epoch_length;
+        config.max_missed_epochs = max_missed_epochs;
+        config.bump = ctx.bumps.validator_config;
+        Ok(())
+    }
+
+    /// `new_admin` may be a governance PDA, after which slashing needs a passed proposal.
+    /// A new `epoch_length` only applies to validators registered afterwards
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_validator_config(
+        ctx: Context<UpdateValidatorConfig>,
+        new_admin: Pubkey,
+        jail_duration: i64,
+        unbonding_period: i64,
+        slash_destination: Pubkey,
+        epoch_length: i64,
+        max_missed_epochs: u64,
+    ) -> Result<()> {
+        require!(
+            jail_duration >= 0 && unbonding_period >= 0,
+            CustomError::InvalidValidatorConfig
+        );
+        require!(
+            epoch_length > 0 && max_missed_epochs > 0,
+            CustomError::InvalidValidatorConfig
+        );
+
+        let config = &mut ctx.accounts.validator_config;
+        config.admin = new_admin;
+        config.jail_duration = jail_duration;
+        config.unbonding_period = unbonding_period;
+        config.slash_destination = slash_destination;
+        config.epoch_length = epoch_length;
+        config.max_missed_epochs = max_missed_epochs;
+        Ok(())
+    }
+
+    /// Takes `bps` of the operator's own stake and of the stake delegated to the
+    /// validator, burning it or sending it to the configured destination, and jails
+    /// the validator. Each piece of evidence can only be used once per validator
+    pub fn slash_validator<'info>(
+        ctx: Context<'_, '_, '_, 'info, SlashValidator<'info>>,
+        evidence_hash: [u8; 32],
+        bps: u16,
+    ) -> Result<()> {
+        require!(bps > 0 && bps < 10_000, CustomError::InvalidSlashBps);
+        require!(
+            ctx.accounts.validator_config.slash_destination == Pubkey::default()
+                || ctx.accounts.slash_destination.is_some(),
+            CustomError::SlashAccountsMissing
+        );
+        // Both fields would be written back on exit, the later one with stale data
+        if let (Some(pool), Some(stake_pool)) = (&ctx.accounts.pool, &ctx.accounts.stake_pool) {
+            require_keys_neq!(
+                pool.key(),
+                stake_pool.key(),
+                CustomError::ValidatorPoolMismatch
+            );
+        }
+
+        let now = Clock::get()?.unix_timestamp;
+        let validator = &mut ctx.accounts.validator;
+
+        // Delegators lose the same fraction: shares stay, the stake behind them shrinks
+        let mut delegated_slashed = 0;
+        if validator.delegated_stake > 0 {
+            let pool = ctx
+                .accounts
+                .pool
+                .as_mut()
+                .ok_or(CustomError::SlashAccountsMissing)?;
+            require!(
+                ctx.accounts.validator_vault.is_some(),
+                CustomError::SlashAccountsMissing
+            );
+            pool.accrue_delegation_rewards(now);
+            validator.accrue_rewards(pool, ctx.accounts.uptime.score_bps(now));
+
+            delegated_slashed = (validator.delegated_stake as u128 * bps as u128 / 10_000) as u64;
+            validator.delegated_stake -= delegated_slashed;
+            pool.delegated_stake = pool.delegated_stake.saturating_sub(delegated_slashed);
+        }
+
+        // The operator's own stake is always slashed when the vault exists
+        let mut self_slashed = 0;
+        let mut stake_vault_bump = None;
+        if !ctx.accounts.stake_vault.data_is_empty() {
+            let stake_vault_info = ctx.accounts.stake_vault.to_account_info();
+            require_keys_eq!(
+                *stake_vault_info.owner,
+                crate::ID,
+                ErrorCode::AccountOwnedByWrongProgram
+            );
+            let mut stake_vault =
+                StakeVault::try_deserialize(&mut &stake_vault_info.try_borrow_data()?[..])?;
+            let stake_vault_ata = ctx
+                .accounts
+                .stake_vault_ata
+                .as_ref()
+                .ok_or(CustomError::SlashAccountsMissing)?;
+            require_keys_eq!(
+                stake_vault_ata.key(),
+                stake_vault.vault,
+                CustomError::InvalidRecipientAccount
+            );
+            self_slashed = (stake_vault.amount as u128 * bps as u128 / 10_000) as u64;
+            stake_vault.amount -= self_slashed;
+            stake_vault.try_serialize(&mut &mut stake_vault_info.try_borrow_mut_data()?[..])?;
+            stake_vault_bump = Some(stake_vault.bump);
+
+            // The vault's pool may be the delegation pool already loaded above
+            let stake_pool = match ctx.accounts.pool.as_mut() {
+                Some(pool) if pool.key() == stake_vault.pool => pool,
+                _ => {
+                    let stake_pool = ctx
+                        .accounts
+                        .stake_pool
+                        .as_mut()
+                        .ok_or(CustomError::SlashAccountsMissing)?;
+                    require_keys_eq!(
+                        stake_pool.key(),
+                        stake_vault.pool,
+                        CustomError::ValidatorPoolMismatch
+                    );
+                    stake_pool
+                }
+            };
+            stake_pool.total_staked = stake_pool.total_staked.saturating_sub(self_slashed);
+
+            let registry = &mut ctx.accounts.registry;
+            registry.total_weight = registry.total_weight.saturating_sub(self_slashed);
+        }
+
+        let validator = &ctx.accounts.validator;
+        let authority = validator.authority;
+        let id_bytes = validator.id.to_le_bytes();
+        let validator_seeds: &[&[u8]] = &[
+            b"validator",
+            authority.as_ref(),
+            &id_bytes,
+            &[validator.bump],
+        ];
+
+        if delegated_slashed > 0 {
+            let vault = ctx.accounts.validator_vault.as_ref().unwrap();
+            slash_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.stake_mint,
+                vault.to_account_info(),
+                ctx.accounts.validator.to_account_info(),
+                ctx.accounts.slash_destination.as_ref(),
+                ctx.remaining_accounts,
+                &[validator_seeds],
+                delegated_slashed,
+            )?;
+        }
+
+        if self_slashed > 0 {
+            let stake_vault_seeds: &[&[u8]] = &[
+                b"stake-vault",
+                authority.as_ref(),
+                &[stake_vault_bump.unwrap()],
+            ];
+            slash_tokens(
+                &ctx.accounts.token_program,
+                &ctx.accounts.stake_mint,
+                ctx.accounts
+                    .stake_vault_ata
+                    .as_ref()
+                    .unwrap()
+                    .to_account_info(),
+                ctx.accounts.stake_vault.to_account_info(),
+                ctx.accounts.slash_destination.as_ref(),
+                ctx.remaining_accounts,
+                &[stake_vault_seeds],
+                self_slashed,
+            )?;
+        }
+
+        let jail_until = now
+            .checked_add(ctx.accounts.validator_config.jail_duration)
+            .ok_or(CustomError::MathOverflow)?;
+        let validator = &mut ctx.accounts.validator;
+        // Pending validators stay unapproved and exiting ones keep unbonding; both
+        // still serve the jail time before they can close
+        if validator.status == ValidatorStatus::Active {
+            validator.status = ValidatorStatus::Jailed;
+            let registry = &mut ctx.accounts.registry;
+            registry.active_count = registry.active_count.saturating_sub(1);
+            registry.set_voting_weight(validator, 0);
+        }
+        validator.jailed_until = validator.jailed_until.max(jail_until);
+
+        let slash_event = &mut ctx.accounts.slash_event;
+        slash_event.validator = validator.key();
+        slash_event.evidence_hash = evidence_hash;
+        slash_event.bps = bps;
+        slash_event.self_slashed = self_slashed;
+        slash_event.delegated_slashed = delegated_slashed;
+        slash_event.destination = ctx.accounts.validator_config.slash_destination;
+        slash_event.slashed_by = ctx.accounts.admin.key();
+        slash_event.timestamp = now;
+        slash_event.bump = ctx.bumps.slash_event;
+
+        msg!(
+            "Slashed validator {} by {} bps: {} own stake, {} delegated, jailed until {}",
+            validator.key(),
+            bps,
+            self_slashed,
+            delegated_slashed,
+            validator.jailed_until
+        );
+        Ok(())
+    }
+
+    pub fn unjail(ctx: Context<Unjail>) -> Result<()> {
+        let validator = &mut ctx.accounts.validator;
+        require!(
+            validator.status == ValidatorStatus::Jailed,
+            CustomError::InvalidValidatorStatus
+        );
+        require!(
+            Clock::get()?.unix_timestamp >= validator.jailed_until,
+            CustomError::ValidatorJailed
+        );
+
+        validator.jailed_until = 0;
+        validator.status = ValidatorStatus::Active;
+
+        let registry = &mut ctx.accounts.registry;
+        registry.active_count = registry
+            .active_count
+            .checked_add(1)
+            .ok_or(CustomError::MathOverflow)?;
+
+        msg!("Validator {} unjailed", validator.key());
+        Ok(())
+    }
+
+    /// Records that the validator is operating this epoch. An inactive validator
+    /// becomes active again, unless it is still serving jail time. Also refreshes
+    /// the validator's share of the registry's active weight
+    pub fn heartbeat(ctx: Context<Heartbeat>) -> Result<()> {
+        let validator = &mut ctx.accounts.validator;
+        require!(
+            validator.status != ValidatorStatus::Exiting,
+            CustomError::InvalidValidatorStatus
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let uptime = &mut ctx.accounts.uptime;
+        let epoch = uptime.epoch_at(now);
+        uptime.record(epoch)?;
+
+        if validator.status == ValidatorStatus::Inactive && now >= validator.jailed_until {
+            validator.status = ValidatorStatus::Active;
+            let registry = &mut ctx.accounts.registry;
+            registry.active_count = registry
+                .active_count
+                .checked_add(1)
+                .ok_or(CustomError::MathOverflow)?;
+        }
+
+        let stake = StakeVault::amount_in(&ctx.accounts.stake_vault)?;
+        ctx.accounts
+            .registry
+            .set_voting_weight(validator, uptime.weigh(stake, now));
+
+        msg!(
+            "Heartbeat from validator {} in epoch {}, uptime {} bps",
+            validator.key(),
+            epoch,
+            uptime.score_bps(now)
+        );
+        Ok(())
+    }
+
+    /// Permissionless: deactivates an active validator that has missed at least
+    /// `max_missed_epochs` heartbeats in a row
+    pub fn crank_uptime(ctx: Context<CrankUptime>) -> Result<()> {
+        let validator = &mut ctx.accounts.validator;
+        require!(
+            validator.status == ValidatorStatus::Active,
+            CustomError::InvalidValidatorStatus
+        );
+
+        let now = Clock::get()?.unix_timestamp;
+        let uptime = &ctx.accounts.uptime;
+        let missed = uptime.missed_epochs(uptime.epoch_at(now));
+        require!(
+            missed >= ctx.accounts.validator_config.max_missed_epochs,
+            CustomError::ValidatorStillLive
+        );
+
+        validator.status = ValidatorStatus::Inactive;
+        let registry = &mut ctx.accounts.registry;
+        registry.active_count = registry.active_count.saturating_sub(1);
+        registry.set_voting_weight(validator, 0);
+
+        msg!(
+            "Validator {} inactive after missing {} epochs",
+            validator.key(),
+            missed
+        );
+        Ok(())
+    }
+
+    /// Permissionless: recounts a validator's share of the registry's active weight
+    /// after its operator's stake changed or it became Active between heartbeats
+    pub fn sync_voting_weight(ctx: Context<SyncVotingWeight>) -> Result<()> {
+        let now = Clock::get()?.unix_timestamp;
+        let stake = StakeVault::amount_in(&ctx.accounts.stake_vault)?;
+        let weight = ctx.accounts.uptime.weigh(stake, now);
+
+        let validator = &mut ctx.accounts.validator;
+        ctx.accounts.registry.set_voting_weight(validator, weight);
+
+        msg!(
+            "Validator {} counts {} towards quorum",
+            validator.key(),
+            validator.voting_weight
+        );
+        Ok(())
+    }
+
+    pub fn init_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, InitEscrow<'info>>,
+        amount_offered: u64,
+        amount_expected: u64,
+        unlock_at: i64,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        require!(
+            unlock_at > clock.unix_timestamp,
+            CustomError::InvalidDeadline
+        );
+
+        // Transfer offered tokens into vault PDA
+        let vault_before = ctx.accounts.vault_amount.amount;
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx
+                    .accounts
+                    .initializer_deposit_token_account
+                    .to_account_info(),
+                mint: ctx.accounts.mint_offered.to_account_info(),
+                to: ctx.accounts.vault_amount.to_account_info(),
+                authority: ctx.accounts.initializer.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            cpi_ctx.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_offered,
+            ctx.accounts.mint_offered.decimals,
+        )?;
+
+        // The escrow releases what the vault actually holds, net of any transfer fee
+        ctx.accounts.vault_amount.reload()?;
+        let amount_offered = ctx
+            .accounts
+            .vault_amount
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.initializer = ctx.accounts.initializer.key();
+        escrow.initializer_deposit_token_account =
+            ctx.accounts.initializer_deposit_token_account.key();
+        escrow.vault_amount = ctx.accounts.vault_amount.key();
+        escrow.mint_offered = ctx.accounts.mint_offered.key();
+        escrow.mint_expected = ctx.accounts.mint_expected.key();
+        escrow.amount_offered = amount_offered;
+        escrow.amount_expected = amount_expected;
+        escrow.unlock_at = unlock_at;
+        escrow.is_fulfilled = false;
+        escrow.bump = ctx.bumps.escrow;
+
+        Ok(())
+    }
+
+    pub fn fulfill_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, FulfillEscrow<'info>>,
+    ) -> Result<()> {
+        let clock = Clock::get()?;
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(!escrow.is_fulfilled, CustomError::AlreadyFulfilled);
+        require!(
+            clock.unix_timestamp >= escrow.unlock_at,
+            CustomError::StakeLocked
+        );
+
+        // Transfer expected tokens from taker -> initializer
+        let pay_initializer_ctx = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.taker_payment_ata.to_account_info(),
+                mint: ctx.accounts.mint_expected.to_account_info(),
+                to: ctx.accounts.initializer_receive_ata.to_account_info(),
+                authority: ctx.accounts.taker.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            pay_initializer_ctx.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            escrow.amount_expected,
+            ctx.accounts.mint_expected.decimals,
+        )?;
+
+        // Transfer offered tokens from vault -> taker
+        let bump = ctx.bumps.vault_authority;
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"vault-authority", escrow.initializer.as_ref(), &[bump]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_amount.to_account_info(),
+                mint: ctx.accounts.mint_offered.to_account_info(),
+                to: ctx.accounts.taker_receive_ata.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            escrow.amount_offered,
+            ctx.accounts.mint_offered.decimals,
+        )?;
+
+        escrow.is_fulfilled = true;
+
+        Ok(())
+    }
+
+    pub fn cancel_escrow<'info>(
+        ctx: Context<'_, '_, '_, 'info, CancelEscrow<'info>>,
+    ) -> Result<()> {
+        let escrow = &mut ctx.accounts.escrow;
+
+        require!(!escrow.is_fulfilled, CustomError::AlreadyFulfilled);
+        require!(
+            ctx.accounts.initializer.key() == escrow.initializer,
+            CustomError::Unauthorized
+        );
+
+        // Return locked tokens back to initializer
+        let bump = ctx.bumps.vault_authority;
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"vault-authority", escrow.initializer.as_ref(), &[bump]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_amount.to_account_info(),
+                mint: ctx.accounts.mint_offered.to_account_info(),
+                to: ctx.accounts.initializer_receive_ata.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            escrow.amount_offered,
+            ctx.accounts.mint_offered.decimals,
+        )?;
+
+        Ok(())
+    }
+
+    pub fn swap<'info>(
+        ctx: Context<'_, '_, '_, 'info, Swap<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        max_amount_in: u64,
+    ) -> Result<()> {
+        let pool = &ctx.accounts.pool;
+
+        let reserve_a = ctx.accounts.input_vault.amount;
+        let reserve_b = ctx.accounts.output_vault.amount;
+
+        require!(amount_in <= max_amount_in, CustomError::SlippageExceeded);
+
+        // Transfer Token A from user -> vault
+        let cpi_ctx_in = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_input_token_account.to_account_info(),
+                mint: ctx.accounts.input_token_mint.to_account_info(),
+                to: ctx.accounts.input_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_in.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_in,
+            ctx.accounts.input_token_mint.decimals,
+        )?;
+
+        // Price the swap on what reached the vault, net of any transfer fee
+        ctx.accounts.input_vault.reload()?;
+        let received = ctx
+            .accounts
+            .input_vault
+            .amount
+            .checked_sub(reserve_a)
+            .ok_or(CustomError::MathOverflow)?;
+
+        // Compute Output
+        let amount_in_with_fee = received * 997;
+        let numerator = amount_in_with_fee * reserve_b;
+        let denominator = (reserve_a * 1000) + amount_in_with_fee;
+        let amount_out = numerator / denominator;
+
+        // Slippage protection
+        require!(amount_out >= min_amount_out, CustomError::SlippageExceeded);
+
+        // Transfer Token B from vault -> user
+        let bump = ctx.bumps.vault_authority;
+        let pool_key = pool.key();
+        let token_out_key = ctx.accounts.output_token_mint.key();
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"vault", pool_key.as_ref(), token_out_key.as_ref(), &[bump]]];
+
+        let cpi_ctx_out = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.output_vault.to_account_info(),
+                mint: ctx.accounts.output_token_mint.to_account_info(),
+                to: ctx.accounts.user_output_token_account.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_out.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_out,
+            ctx.accounts.output_token_mint.decimals,
+        )?;
+
+        msg!(
+            "Swapped {} → {} (min expected {}, max allowed in {})",
+            amount_in,
+            amount_out,
+            min_amount_out,
+            max_amount_in
+        );
+
+        Ok(())
+    }
+
+    pub fn add_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddLiquidity<'info>>,
+        amount_a: u64,
+        amount_b: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let reserve_a = ctx.accounts.token_a_vault.amount;
+        let reserve_b = ctx.accounts.token_b_vault.amount;
+
+        let total_lp_supply = pool.total_lp_supply;
+
+        // Step 1 - Transfer user tokens into vaults
+        let cpi_ctx_a = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_token_a.to_account_info(),
+                mint: ctx.accounts.token_a_mint.to_account_info(),
+                to: ctx.accounts.token_a_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_a.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_a,
+            ctx.accounts.token_a_mint.decimals,
+        )?;
+
+        // Step 2 - Transfer token B from user to vault
+        let cpi_ctx_b = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_token_b.to_account_info(),
+                mint: ctx.accounts.token_b_mint.to_account_info(),
+                to: ctx.accounts.token_b_vault.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_b.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_b,
+            ctx.accounts.token_b_mint.decimals,
+        )?;
+
+        // LP shares follow what the vaults received, net of any transfer fee
+        ctx.accounts.token_a_vault.reload()?;
+        ctx.accounts.token_b_vault.reload()?;
+        let amount_a = ctx
+            .accounts
+            .token_a_vault
+            .amount
+            .checked_sub(reserve_a)
+            .ok_or(CustomError::MathOverflow)?;
+        let amount_b = ctx
+            .accounts
+            .token_b_vault
+            .amount
+            .checked_sub(reserve_b)
+            .ok_or(CustomError::MathOverflow)?;
+
+        // Step 3 - Calculate LP tokens to mint
+        let lp_to_mint = if total_lp_supply == 0 {
+            // Frist LP - gets sqrt(amount_a * amount_b)
+            integer_sqrt(amount_a.checked_mul(amount_b).unwrap())
+        } else {
+            let lp_from_a = amount_a * total_lp_supply / reserve_a;
+            let lp_from_b = amount_b * total_lp_supply / reserve_b;
+            lp_from_a.min(lp_from_b)
+        };
+
+        // Step 4 - Mint LP tokens to user using vault PDA as mint authority
+        let bump = ctx.bumps.vault_authority;
+        let pool_key = pool.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"vault-authority", pool_key.as_ref(), &[bump]]];
+
+        let cpi_ctx_mint = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            MintTo {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                to: ctx.accounts.user_lp_token_account.to_account_info(),
+                authority: ctx.accounts.vault_authority.to_account_info(),
+            },
+            signer_seeds,
+        );
+        token_interface::mint_to(cpi_ctx_mint, lp_to_mint)?;
+
+        // Step 5 - Update LP supply in pool
+        pool.total_lp_supply = pool.total_lp_supply.checked_add(lp_to_mint).unwrap();
+
+        Ok(())
+    }
+
+    pub fn remove_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveLiquidity<'info>>,
+        lp_amount: u64,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool;
+        let reserve_a = ctx.accounts.token_a_vault.amount;
+        let reserve_b = ctx.accounts.token_b_vault.amount;
+
+        let total_lp_supply = pool.total_lp_supply;
+        require!(total_lp_supply > 0, CustomError::NoLiquidity);
+
+        // Calculate the share of tokens to return
+        let amount_a = lp_amount
+            .checked_mul(reserve_a)
+            .unwrap()
+            .checked_div(total_lp_supply)
+            .unwrap();
+
+        let amount_b = lp_amount
+            .checked_mul(reserve_b)
+            .unwrap()
+            .checked_div(total_lp_supply)
+            .unwrap();
+
+        // Step 1 - Burn LP tokens from user
+        let cpi_ctx_burn = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            Burn {
+                mint: ctx.accounts.lp_mint.to_account_info(),
+                from: ctx.accounts.user_lp_token.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        token_interface::burn(cpi_ctx_burn, lp_amount)?;
+
+        // Step 2 - Transfer token A to user
+        let bump = ctx.bumps.vault_authority;
+        let pool_key = pool.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"vault-authority", pool_key.as_ref(), &[bump]]];
+
+        let cpi_ctx_a = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.token_a_vault.to_account_info(),
+                mint: ctx.accounts.token_a_mint.to_account_info(),
+                to: ctx.accounts.user_token_a.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_a.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_a,
+            ctx.accounts.token_a_mint.decimals,
+        )?;
+
+        // Step 3 - Transfer token B to user
+        let cpi_ctx_b = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.token_b_vault.to_account_info(),
+                mint: ctx.accounts.token_b_mint.to_account_info(),
+                to: ctx.accounts.user_token_b.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_b.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_b,
+            ctx.accounts.token_b_mint.decimals,
+        )?;
+
+        // Step 4 - Update LP supply
+        pool.total_lp_supply = pool.total_lp_supply.checked_sub(lp_amount).unwrap();
+
+        Ok(())
+    }
+
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        owners: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        require!(
+            owners.len() <= Multisig::MAX_OWNERS,
+            CustomError::TooManyOwners
+        );
+        require!(
+            threshold > 0 && threshold <= owners.len() as u8,
+            CustomError::InvalidThreshold
+        );
+
+        let multisig = &mut ctx.accounts.multisig;
+
+        multisig.owners = owners;
+        multisig.threshold = threshold;
+        multisig.bump = ctx.bumps.multisig;
+        multisig.owner_set_seqno = 0;
+
+        Ok(())
+    }
+
+    pub fn propose_transaction(
+        ctx: Context<ProposeTransaction>,
+        program_id: Pubkey,
+        accounts: Vec<TransactionAccount>,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let proposer = &ctx.accounts.proposer;
+
+        // Validator proposer is an owner
+        require!(
+            multisig.owners.contains(&proposer.key()),
+            CustomError::NotAnOwner
+        );
+
+        // Populate transaction
+        let tx = &mut ctx.accounts.tx;
+        tx.multisig = multisig.key();
+        tx.program_id = program_id;
+        tx.accounts = accounts;
+        tx.data = data;
+        tx.did_execute = false;
+        tx.signers = [false; 10];
+
+        // Mark the propose as approved
+        if let Some(index) = multisig.owners.iter().position(|k| k == &proposer.key()) {
+            tx.signers[index] = true;
+        } else {
+            return Err(CustomError::NotAnOwner.into());
+        }
+        tx.owner_set_seqno = multisig.owner_set_seqno;
+
+        Ok(())
+    }
+
+    pub fn approve_transaction(ctx: Context<ApproveTransaction>) -> Result<()> {
+        let multisig = &ctx.accounts.multisig;
+        let signer = &ctx.accounts.signer;
+        let tx = &mut ctx.accounts.tx;
+
+        // Check signer is one of the owners
+        if let Some(index) = multisig.owners.iter().position(|k| k == &signer.key()) {
+            // Ensure signer hasnt already signed
+            require!(!tx.signers[index], CustomError::AlreadySigned);
+
+            tx.signers[index] = true;
+            Ok(())
+        } else {
+            Err(CustomError::NotAnOwner.into())
+        }
+    }
+
+    pub fn execute_transaction(ctx: Context<ExecuteTransaction>) -> Result<()> {
+        let tx = &mut ctx.accounts.tx;
+        let multisig = &ctx.accounts.multisig;
+
+        // Count how many owners signed
+        let sig_count = tx
+            .signers
+            .iter()
+            .zip(multisig.owners.iter())
+            .filter(|(signed, _)| **signed)
+            .count();
+
+        // Ensure enough approvals
+        require!(
+            sig_count >= multisig.threshold as usize,
+            CustomError::InsufficientSignatures
+        );
+
+        // Mark tx as executed
+        tx.did_execute = true;
+
+        // CPI to the intended program
+        let account_infos: Vec<AccountInfo> = ctx.remaining_accounts.to_vec();
+
+        let ix = anchor_lang::solana_program::instruction::Instruction {
+            program_id: tx.program_id,
+            accounts: tx
+                .accounts
+                .iter()
+                .map(
+                    |acc| anchor_lang::solana_program::instruction::AccountMeta {
+                        pubkey: acc.pubkey,
+                        is_signer: acc.is_signer,
+                        is_writable: acc.is_writable,
+                    },
+                )
+                .collect(),
+            data: tx.data.clone(),
+        };
+
+        anchor_lang::solana_program::program::invoke(&ix, &account_infos)?;
+
+        Ok(())
+    }
+
+    pub fn init_pool_clmm(
+        ctx: Context<InitPoolClmm>,
+        sqrt_price_x64: u128,
+        current_tick: i32,
+        tick_spacing: u16,
+        fee_rate: u16,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_clmm;
+
+        pool.token_a_mint = ctx.accounts.token_a_mint.key();
+        pool.token_b_mint = ctx.accounts.token_b_mint.key();
+        pool.vault_a = ctx.accounts.vault_a.key();
+        pool.vault_b = ctx.accounts.vault_b.key();
+
+        pool.sqrt_price_x64 = sqrt_price_x64;
+        pool.current_tick = current_tick;
+        pool.tick_spacing = tick_spacing;
+        pool.liquidity = 0;
+        pool.fee_rate = fee_rate;
+        pool.fee_growth_global_a = 0;
+        pool.fee_growth_global_b = 0;
+
+        pool.bump = ctx.bumps.pool_clmm;
+        pool.signer_bump = ctx.bumps.pool_signer;
+
+        Ok(())
+    }
+
+    pub fn init_tick(ctx: Context<InitTick>, tick_index: i32) -> Result<()> {
+        let tick = &mut ctx.accounts.tick;
+        tick.tick_index = tick_index;
+        tick.liquidity_net = 0;
+        tick.fee_growth_outside_a = 0;
+        tick.fee_growth_outside_b = 0;
+        Ok(())
+    }
+
+    pub fn open_position<'info>(
+        ctx: Context<'_, '_, '_, 'info, OpenPosition<'info>>,
+        liquidity: u128,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_clmm;
+        let tick_lower_account = &mut ctx.accounts.tick_lower;
+        let tick_upper_account = &mut ctx.accounts.tick_upper;
+
+        // Validate
+        require!(tick_lower < tick_upper, CustomError::InvalidTickRange);
+        require!(liquidity > 0, CustomError::ZeroLiquidity);
+
+        // Update ticks
+        tick_lower_account.liquidity_net = tick_lower_account
+            .liquidity_net
+            .checked_add(liquidity as i128)
+            .unwrap();
+        tick_upper_account.liquidity_net = tick_upper_account
+            .liquidity_net
+            .checked_sub(liquidity as i128)
+            .unwrap();
+
+        // Inline liquidity-to-token calculation (placeholder for now)
+        let amount_a = (liquidity / 1000) as u64;
+        let amount_b = (liquidity / 1000) as u64;
+
+        // Cpi - Transfer token A
+        let cpi_ctx_a = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_token_a.to_account_info(),
+                mint: ctx.accounts.token_a_mint.to_account_info(),
+                to: ctx.accounts.vault_a.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_a.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_a,
+            ctx.accounts.token_a_mint.decimals,
+        )?;
+
+        // Cpi - Transfer token B
+        let cpi_ctx_b = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_token_b.to_account_info(),
+                mint: ctx.accounts.token_b_mint.to_account_info(),
+                to: ctx.accounts.vault_b.to_account_info(),
+                authority: ctx.accounts.owner.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_b.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_b,
+            ctx.accounts.token_b_mint.decimals,
+        )?;
+
+        // Store user_position
+        let user_position = &mut ctx.accounts.user_position;
+        user_position.owner = ctx.accounts.owner.key();
+        user_position.pool_clmm = pool.key();
+        user_position.tick_lower = tick_lower;
+        user_position.tick_upper = tick_upper;
+        user_position.liquidity = liquidity;
+        user_position.fee_growth_checkpoint_a = 0;
+        user_position.fee_growth_checkpoint_b = 0;
+
+        // If tick is active update pool liquidity
+        if pool.current_tick >= tick_lower && pool.current_tick < tick_upper {
+            pool.liquidity = pool.liquidity.checked_add(liquidity).unwrap();
+        }
+
+        msg!(
+            "Position opened: {} liquidity between ticks {} - {}",
+            liquidity,
+            tick_lower,
+            tick_upper
+        );
+
+        Ok(())
+    }
+
+    pub fn decrease_liquidity<'info>(
+        ctx: Context<'_, '_, '_, 'info, DecreaseLiquidity<'info>>,
+        liquidity_to_remove: u128,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_clmm;
+        let tick_lower = &mut ctx.accounts.tick_lower;
+        let tick_upper = &mut ctx.accounts.tick_upper;
+        let user_position = &mut ctx.accounts.user_position;
+
+        // Safety checks
+        require!(liquidity_to_remove > 0, CustomError::ZeroLiquidity);
+        require!(
+            liquidity_to_remove <= user_position.liquidity,
+            CustomError::InvalidLiquidityAmount
+        );
+
+        // Update tick liquidity_net
+        tick_lower.liquidity_net = tick_lower
+            .liquidity_net
+            .checked_sub(liquidity_to_remove as i128)
+            .unwrap();
+        tick_upper.liquidity_net = tick_upper
+            .liquidity_net
+            .checked_add(liquidity_to_remove as i128)
+            .unwrap();
+
+        // Update pool liquidity if position is currently active
+        if pool.current_tick >= tick_lower.tick_index && pool.current_tick < tick_upper.tick_index {
+            pool.liquidity = pool.liquidity.checked_sub(liquidity_to_remove).unwrap()
+        }
+
+        // Placeholder right now (Dummy token A & B to return)
+        let amount_a = (liquidity_to_remove / 1000) as u64;
+        let amount_b = (liquidity_to_remove / 1000) as u64;
+
+        // CPI transfer token A back to the user
+        let pool_key = pool.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"signer", pool_key.as_ref(), &[pool.signer_bump]]];
+
+        let cpi_ctx_a = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_a.to_account_info(),
+                mint: ctx.accounts.token_a_mint.to_account_info(),
+                to: ctx.accounts.user_token_a.to_account_info(),
+                authority: ctx.accounts.pool_signer.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_a.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_a,
+            ctx.accounts.token_a_mint.decimals,
+        )?;
+
+        // Cpi Transfer token B back to user
+        let cpi_ctx_b = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_b.to_account_info(),
+                mint: ctx.accounts.token_b_mint.to_account_info(),
+                to: ctx.accounts.user_token_b.to_account_info(),
+                authority: ctx.accounts.pool_signer.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_b.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_b,
+            ctx.accounts.token_b_mint.decimals,
+        )?;
+
+        // Update users position
+        user_position.liquidity = user_position
+            .liquidity
+            .checked_sub(liquidity_to_remove)
+            .unwrap();
+
+        msg!(
+            "Liquidity removed: {} → A: {}, B: {} | Remaining: {}",
+            liquidity_to_remove,
+            amount_a,
+            amount_b,
+            user_position.liquidity
+        );
+
+        Ok(())
+    }
+
+    pub fn collect_fees<'info>(ctx: Context<'_, '_, '_, 'info, CollectFees<'info>>) -> Result<()> {
+        let pool = &ctx.accounts.pool_clmm;
+        let user_position = &mut ctx.accounts.user_position;
+
+        // Assuming 10 tokens of A and B
+        let earned_a: u64 = 10;
+        let earned_b: u64 = 10;
+
+        // PDA Signer setup
+        let pool_key = pool.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"signer", pool_key.as_ref(), &[pool.signer_bump]]];
+
+        // Transfer A
+        let cpi_ctx_a = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_a.to_account_info(),
+                mint: ctx.accounts.token_a_mint.to_account_info(),
+                to: ctx.accounts.user_token_a.to_account_info(),
+                authority: ctx.accounts.pool_signer.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_a.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            earned_a,
+            ctx.accounts.token_a_mint.decimals,
+        )?;
+
+        // Transfer B
+        let cpi_ctx_b = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_b.to_account_info(),
+                mint: ctx.accounts.token_b_mint.to_account_info(),
+                to: ctx.accounts.user_token_b.to_account_info(),
+                authority: ctx.accounts.pool_signer.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_b.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            earned_b,
+            ctx.accounts.token_b_mint.decimals,
+        )?;
+
+        // Update fee checkpoints (placeholder — real values will come from pool)
+        user_position.fee_growth_checkpoint_a = pool.fee_growth_global_a;
+        user_position.fee_growth_checkpoint_b = pool.fee_growth_global_b;
+
+        msg!("Fees collected: {} A and {} B", earned_a, earned_b);
+
+        Ok(())
+    }
+
+    pub fn swap_clmm<'info>(
+        ctx: Context<'_, '_, '_, 'info, SwapClmm<'info>>,
+        amount_in: u64,
+        min_amount_out: u64,
+        a_to_b: bool,
+    ) -> Result<()> {
+        // --- Token Transfer In: From User → Vault ---
+        let vault_before = ctx.accounts.vault_source.amount;
+        let cpi_ctx_in = CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.user_source_token.to_account_info(),
+                mint: ctx.accounts.source_mint.to_account_info(),
+                to: ctx.accounts.vault_source.to_account_info(),
+                authority: ctx.accounts.user.to_account_info(),
+            },
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_in.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_in,
+            ctx.accounts.source_mint.decimals,
+        )?;
+
+        // Price the swap on what reached the vault, net of any transfer fee
+        ctx.accounts.vault_source.reload()?;
+        let received = ctx
+            .accounts
+            .vault_source
+            .amount
+            .checked_sub(vault_before)
+            .ok_or(CustomError::MathOverflow)?;
+
+        let pool = &mut ctx.accounts.pool_clmm;
+
+        // Fee handlding
+        let fee_rate = pool.fee_rate as u64;
+        let fee_amount = received
+            .checked_mul(fee_rate)
+            .unwrap()
+            .checked_div(10_000)
+            .unwrap();
+
+        let amount_in_after_fee = received
+            .checked_sub(fee_amount)
+            .ok_or(CustomError::MathOverflow)?;
+        let amount_out = amount_in_after_fee; // 1:1 for now
+
+        // --- Validation ---
+        require!(amount_out >= min_amount_out, CustomError::SlippageExceeded);
+
+        // Fee growth update
+        if let Some(fee_growth) = ((fee_amount as u128) << 64).checked_div(pool.liquidity) {
+            if a_to_b {
+                pool.fee_growth_global_a =
+                    pool.fee_growth_global_a.checked_add(fee_growth).unwrap();
+            } else {
+                pool.fee_growth_global_b =
+                    pool.fee_growth_global_b.checked_add(fee_growth).unwrap();
+            }
+        }
+
+        // --- Token Transfer Out: From Vault → User (Signer PDA) ---
+        let pool_key = pool.key();
+        let signer_seeds: &[&[&[u8]]] =
+            &[&[b"signer", pool_key.as_ref(), &[ctx.bumps.pool_signer]]];
+
+        let cpi_ctx_out = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: ctx.accounts.vault_destination.to_account_info(),
+                mint: ctx.accounts.destination_mint.to_account_info(),
+                to: ctx.accounts.user_destination_token.to_account_info(),
+                authority: ctx.accounts.pool_signer.to_account_info(),
+            },
+            signer_seeds,
+        );
+        transfer_checked_with_hook(
+            cpi_ctx_out.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+            amount_out,
+            ctx.accounts.destination_mint.decimals,
+        )?;
+
+        msg!(
+            "Swapped {} (A→B: {}) → {}, fee: {}",
+            received,
+            a_to_b,
+            amount_out,
+            fee_amount
+        );
+
+        Ok(())
+    }
+
+    pub fn close_position(
+        ctx: Context<ClosePosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        let user_position = &ctx.accounts.user_position;
+
+        // Only allow closing if all liquidity is removed
+        require!(
+            user_position.liquidity == 0,
+            CustomError::PositionStillHasLiquidity
+        );
+
+        msg!(
+            "Position closed for user: {} | tick range: [{} - {}]",
+            ctx.accounts.owner.key(),
+            tick_lower,
+            tick_upper
+        );
+
+        Ok(())
+    }
+
+    pub fn collect_protocol_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, CollectProtocolFees<'info>>,
+    ) -> Result<()> {
+        let pool = &mut ctx.accounts.pool_clmm;
+
+        let fee_a = pool.protocol_fee_a;
+        let fee_b = pool.protocol_fee_b;
+
+        require!(fee_a > 0 || fee_b > 0, CustomError::NoProtocolFeesToCollect);
+
+        let pool_key = pool.key();
+        let signer_seeds: &[&[&[u8]]] = &[&[b"signer", pool_key.as_ref(), &[pool.signer_bump]]];
+
+        // Transfer token A protocol fee
+        if fee_a > 0 {
+            let cpi_ctx_a = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_a.to_account_info(),
+                    mint: ctx.accounts.token_a_mint.to_account_info(),
+                    to: ctx.accounts.protocol_fee_recipient_a.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                signer_seeds,
+            );
+            transfer_checked_with_hook(
+                cpi_ctx_a.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+                fee_a,
+                ctx.accounts.token_a_mint.decimals,
+            )?;
+        }
+
+        // Transfer token B protocol fee
+        if fee_b > 0 {
+            let cpi_ctx_b = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                TransferChecked {
+                    from: ctx.accounts.vault_b.to_account_info(),
+                    mint: ctx.accounts.token_b_mint.to_account_info(),
+                    to: ctx.accounts.protocol_fee_recipient_b.to_account_info(),
+                    authority: ctx.accounts.pool_signer.to_account_info(),
+                },
+                signer_seeds,
+            );
+            transfer_checked_with_hook(
+                cpi_ctx_b.with_remaining_accounts(ctx.remaining_accounts.to_vec()),
+                fee_b,
+                ctx.accounts.token_b_mint.decimals,
+            )?;
+        }
+
+        // Reset protocol fees after collection
+        pool.protocol_fee_a = 0;
+        pool.protocol_fee_b = 0;
+
+        msg!("Protocol fees collected: {} A, {} B", fee_a, fee_b);
+
+        Ok(())
+    }
+
+    //     pub fn handle_buy_nft(
+    //         ctx: Context<BuyNft>,
+    //     ) -> Result<()> {
+    //         let ata = associated_token::get_associated_token_address(
+    //             &ctx.accounts.buyer_authority.key(),
+    //             &ctx.accounts.nft_mint.key());
+    //         require!(ata == ctx.accounts.buyer_nft_account.key(), CustomError::AlreadyExecuted);
+
+    //         if ctx.accounts.buyer_nft_account.data_len() == 0{
+    //             //create buyer nft token account
+    //             associated_token::create(CpiContext::new(ctx.accounts.associated_token_program.to_account_info(),
+    //             associated_token:: Create{
+    //                     payer: ctx.accounts.buyer_authority.to_account_info(),
+    //                     associated_token: ctx.accounts.buyer_nft_account.to_account_info(),
+    //                     authority: ctx.accounts.buyer_authority.to_account_info(),
+    //                     mint: ctx.accounts.nft_mint.to_account_info(),
+    //                     system_program: ctx.accounts.system_program.to_account_info(),
+    //                     token_program: ctx.accounts.token_program.to_account_info(),
+    //                 }
+    //             ))
+    //         } else {
+
+    //             Ok(())
+    //         }
+    // }
+}
+
+// ----------------- CONTEXT STRUCTS ---------------------
+
+// #[derive(Accounts)]
+// pub struct BuyNft<'info> {
+//     #[account(mut)]
+//     pub buyer_authority: Signer<'info>,
+//     #[account(mut)]
+//     pub buyer_nft_account: AccountInfo<'info>,
+//     pub nft_mint: Account<'info, Mint>,
+//     pub system_program: Program<'info, System>,
+//     pub token_program: Program<'info, Token>,
+//     pub associated_token_program: Program<'info, AssociatedToken>,
+// }
+#[derive(Accounts)]
+#[instruction(name: String)]
+pub struct InitProfile<'info> {
+    #[account(
+        init,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump,
+        payer = authority,
+        space = UserProfile::space(&name, "", "", "")
+    )]
+    pub profile: Account<'info, UserProfile>, // The Account type will check that the owner of the account being loaded is actually owned by the program
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, bio: String, avatar_uri: String, website: String)]
+pub struct UpdateProfile<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority,
+        realloc = UserProfile::space(&name, &bio, &avatar_uri, &website),
+        realloc::payer = authority,
+        realloc::zero = false
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProfile<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(handle: String)]
+pub struct ClaimHandle<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    // One PDA per normalized handle, so "Alice" and "alice" collide
+    #[account(
+        init,
+        seeds = [b"handle".as_ref(), &handle_seed(&handle)],
+        bump,
+        payer = authority,
+        space = HandleRecord::LEN
+    )]
+    pub handle_record: Account<'info, HandleRecord>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseHandle<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"handle".as_ref(), &handle_seed(&profile.handle)],
+        bump = handle_record.bump,
+        has_one = profile @ CustomError::Unauthorized
+    )]
+    pub handle_record: Account<'info, HandleRecord>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct TransferHandle<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = from_profile.bump,
+        has_one = authority
+    )]
+    pub from_profile: Account<'info, UserProfile>,
+
+    // Receiving side signs too, so nobody can be handed a handle they didn't ask for
+    #[account(
+        mut,
+        seeds = [b"profile", new_authority.key().as_ref()],
+        bump = to_profile.bump,
+        constraint = to_profile.authority == new_authority.key() @ CustomError::Unauthorized
+    )]
+    pub to_profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"handle".as_ref(), &handle_seed(&from_profile.handle)],
+        bump = handle_record.bump,
+        constraint = handle_record.profile == from_profile.key() @ CustomError::Unauthorized
+    )]
+    pub handle_record: Account<'info, HandleRecord>,
+
+    pub authority: Signer<'info>,
+    pub new_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetGuardians<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeRotation<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveRecovery<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", profile.authority.as_ref()],
+        bump = profile.bump
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    pub guardian: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CancelRotation<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RotateAuthority<'info> {
+    // Rent from the old profile goes to the new key, the old wallet may be lost
+    #[account(
+        mut,
+        close = new_authority,
+        seeds = [b"profile", old_profile.authority.as_ref()],
+        bump = old_profile.bump
+    )]
+    pub old_profile: Account<'info, UserProfile>,
+
+    #[account(
+        init,
+        seeds = [b"profile", new_authority.key().as_ref()],
+        bump,
+        payer = new_authority,
+        space = UserProfile::space(
+            &old_profile.name,
+            &old_profile.bio,
+            &old_profile.avatar_uri,
+            &old_profile.website
+        )
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    // Only required when the old profile holds a handle
+    #[account(
+        mut,
+        seeds = [b"handle".as_ref(), &handle_seed(&old_profile.handle)],
+        bump = handle_record.bump,
+        constraint = handle_record.profile == old_profile.key() @ CustomError::Unauthorized
+    )]
+    pub handle_record: Option<Account<'info, HandleRecord>>,
+
+    #[account(mut)]
+    pub new_authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateValidator<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [
+            b"validator",
+            profile.previous_authority.as_ref(),
+            &old_validator.id.to_le_bytes()
+        ],
+        bump = old_validator.bump,
+        constraint = old_validator.authority == profile.previous_authority @ CustomError::Unauthorized,
+        // Delegations and their vault are keyed by the old validator address
+        constraint = old_validator.total_shares == 0 @ CustomError::ValidatorHasDelegations
+    )]
+    pub old_validator: Account<'info, ValidatorInfo>,
+
+    #[account(
+        init,
+        seeds = [b"validator", authority.key().as_ref(), &old_validator.id.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = ValidatorInfo::LEN
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"registry-page".as_ref(), &RegistryPage::page_of(old_validator.id).to_le_bytes()],
+        bump = registry_page.bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+}
+
+#[derive(Accounts)]
+pub struct MigrateStakeVault<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"stake-vault", profile.previous_authority.as_ref()],
+        bump = old_stake_vault.bump,
+        constraint = old_stake_vault.owner == profile.previous_authority @ CustomError::Unauthorized
+    )]
+    pub old_stake_vault: Account<'info, StakeVault>,
+
+    #[account(
+        mut,
+        associated_token::mint = stake_mint,
+        associated_token::authority = old_stake_vault,
+        associated_token::token_program = token_program
+    )]
+    pub old_vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init,
+        seeds = [b"stake-vault", authority.key().as_ref()],
+        bump,
+        payer = authority,
+        space = StakeVault::LEN
+    )]
+    pub stake_vault: Account<'info, StakeVault>,
+
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = stake_mint,
+        associated_token::authority = stake_vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(session_key: Pubkey)]
+pub struct CreateSession<'info> {
+    #[account(
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        init,
+        seeds = [b"session", profile.key().as_ref(), session_key.as_ref()],
+        bump,
+        payer = authority,
+        space = SessionKey::LEN
+    )]
+    pub session: Account<'info, SessionKey>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeSession<'info> {
+    #[account(
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"session", profile.key().as_ref(), session.session_key.as_ref()],
+        bump = session.bump,
+        has_one = authority
+    )]
+    pub session: Account<'info, SessionKey>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CreateMint<'info> {
+    #[account(
+        init_if_needed,
+        payer = payer,
+        mint::decimals = 9,
+        mint::authority = mint_authority,
+        mint::freeze_authority = mint_authority,
+        mint::token_program = token_program,
+        seeds = [b"global-mint"],
+        bump
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        seeds = [b"mint-config"],
+        bump,
+        space = MintConfig::LEN
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    /// CHECK: PDA mint authority, validated via seed constraints
+    #[account(seeds = [b"mint-authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>, // UncheckedAccount is an alias for AccountInfo. This does not check for ownership, so care must be taken as it will accept arbitrary accounts.
+
+    #[account(mut)]
+    pub payer: Signer<'info>, // This type will check that the Signer account signed the transaction; it checks that the signature matches the public key of the account.
+
+    // The creator becomes the mint-config admin, so only the upgrade authority may create it
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::Smartolana>,
+
+    #[account(
+        constraint = program_data.upgrade_authority_address == Some(payer.key()) @ CustomError::Unauthorized
+    )]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitValidatorRegistry<'info> {
+    #[account(
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        has_one = admin
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"validator-registry"],
+        bump,
+        space = ValidatorRegistry::LEN
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitValidator<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+
+    // The page holding the next id; the first validator of a page creates it
+    #[account(
+        init_if_needed,
+        payer = authority,
+        seeds = [b"registry-page".as_ref(), &RegistryPage::page_of(registry.next_id).to_le_bytes()],
+        bump,
+        space = RegistryPage::LEN
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+
+    #[account(
+        init,
+        seeds = [b"validator", authority.key().as_ref(), &registry.next_id.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = ValidatorInfo::LEN
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(seeds = [b"validator-config"], bump = validator_config.bump)]
+    pub validator_config: Account<'info, ValidatorConfig>,
+
+    // Keyed by id so it survives authority migration
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"uptime".as_ref(), &registry.next_id.to_le_bytes()],
+        bump,
+        space = ValidatorUptime::LEN
+    )]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    // The ATA outlives close_validator and may already hold tokens
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = mint,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program
+    )]
+    pub validator_ata: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: Mint authority PDA
+    #[account(seeds = [b"mint-authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"global-mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        has_one = mint
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateValidator<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        has_one = authority,
+        has_one = profile
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveValidator<'info> {
+    #[account(
+        seeds = [b"validator-config"],
+        bump = validator_config.bump,
+        has_one = admin
+    )]
+    pub validator_config: Account<'info, ValidatorConfig>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator", validator.authority.as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct BeginExit<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        has_one = authority
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    pub authority: Signer<'info>,
+
+    #[account(seeds = [b"validator-config"], bump = validator_config.bump)]
+    pub validator_config: Account<'info, ValidatorConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct CloseValidator<'info> {
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        has_one = authority,
+        has_one = profile,
+        constraint = validator.total_shares == 0 @ CustomError::ValidatorHasDelegations
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+
+    #[account(
+        mut,
+        seeds = [b"registry-page".as_ref(), &RegistryPage::page_of(validator.id).to_le_bytes()],
+        bump = registry_page.bump
+    )]
+    pub registry_page: Account<'info, RegistryPage>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()],
+        bump = uptime.bump
+    )]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(
+        mut,
+        associated_token::mint = mint,
+        associated_token::authority = authority,
+        associated_token::token_program = token_program
+    )]
+    pub validator_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, seeds = [b"global-mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct TransferTokens<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub from: InterfaceAccount<'info, TokenAccount>, // SENDERS ATA
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub to: InterfaceAccount<'info, TokenAccount>, // RECEIVERS ATA
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: blocklist entry of the sender, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", sender.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&mint.key(), &sender_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub sender_blocklist_entry: UncheckedAccount<'info>,
+
+    /// CHECK: blocklist entry of the recipient, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", to.owner.as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&mint.key(), &recipient_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub recipient_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct BatchTransfer<'info> {
+    #[account(mut)]
+    pub sender: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = sender,
+        token::token_program = token_program
+    )]
+    pub from: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mint::token_program = token_program)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: blocklist entry of the sender, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", sender.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&mint.key(), &sender_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub sender_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ApproveAllowance<'info> {
+    #[account(
+        init,
+        payer = owner,
+        seeds = [
+            b"allowance",
+            owner.key().as_ref(),
+            spender.key().as_ref(),
+            source.key().as_ref()
+        ],
+        bump,
+        space = Allowance::LEN
+    )]
+    pub allowance: Account<'info, Allowance>,
+
+    /// CHECK: any key, including another program's PDA, may be a spender
+    pub spender: UncheckedAccount<'info>,
+
+    /// CHECK: PDA that holds the SPL delegation, validated via seed constraints
+    #[account(seeds = [b"allowance-delegate", owner.key().as_ref()], bump)]
+    pub delegate: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = owner,
+        token::token_program = token_program
+    )]
+    pub source: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeAllowance<'info> {
+    #[account(
+        mut,
+        close = owner,
+        seeds = [
+            b"allowance",
+            owner.key().as_ref(),
+            allowance.spender.as_ref(),
+            source.key().as_ref()
+        ],
+        bump = allowance.bump,
+        has_one = owner,
+        has_one = source
+    )]
+    pub allowance: Account<'info, Allowance>,
+
+    /// CHECK: PDA that holds the SPL delegation, validated via seed constraints
+    #[account(
+        seeds = [b"allowance-delegate", owner.key().as_ref()],
+        bump = allowance.delegate_bump
+    )]
+    pub delegate: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub source: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct TransferFrom<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"allowance",
+            allowance.owner.as_ref(),
+            spender.key().as_ref(),
+            source.key().as_ref()
+        ],
+        bump = allowance.bump,
+        has_one = spender,
+        has_one = source,
+        has_one = mint
+    )]
+    pub allowance: Account<'info, Allowance>,
+
+    pub spender: Signer<'info>,
+
+    /// CHECK: PDA that holds the SPL delegation, validated via seed constraints
+    #[account(
+        seeds = [b"allowance-delegate", allowance.owner.as_ref()],
+        bump = allowance.delegate_bump
+    )]
+    pub delegate: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub source: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub destination: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: blocklist entry of the allowance owner, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", allowance.owner.as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&mint.key(), &owner_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub owner_blocklist_entry: UncheckedAccount<'info>,
+
+    /// CHECK: blocklist entry of the recipient, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", destination.owner.as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&mint.key(), &recipient_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub recipient_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+impl BatchTransfer<'_> {
+    /// A transaction may lock at most 64 accounts. Nine go to the fixed accounts, the program
+    /// and a compute budget instruction, leaving room for 18 triples even with lookup tables.
+    /// Without lookup tables the 1232 byte packet fits far fewer, and creating ATAs costs
+    /// ~30k CU each, so callers raise the compute limit and chunk larger lists across transactions
+    pub const MAX_RECIPIENTS: usize = 18;
+}
+
+impl<'info> TransferTokens<'info> {
+    pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, TransferChecked<'info>> {
+        let cpi_accounts = TransferChecked {
+            from: self.from.to_account_info(),
+            mint: self.mint.to_account_info(),
+            to: self.to.to_account_info(),
+            authority: self.sender.to_account_info(),
+        };
+        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
+    }
+}
+
+#[derive(Accounts)]
+pub struct BurnTokens<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = owner_ata.owner == owner.key(),
+        constraint = owner_ata.mint == mint.key()
+    )]
+    pub owner_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeMintAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        has_one = admin
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptMintAuthority<'info> {
+    #[account(
+        mut,
+        seeds = [b"global-mint"],
+        bump
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: PDA mint authority, validated via seed constraints
+    #[account(seeds = [b"mint-authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        has_one = mint,
+        constraint = mint_config.pending_mint_authority == new_authority.key() @ CustomError::Unauthorized
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    pub new_authority: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitMintMetadata<'info> {
+    // Metadata can only be attached while the program still controls the mint
+    #[account(
+        seeds = [b"global-mint"],
+        bump,
+        constraint = mint.mint_authority == COption::Some(mint_authority.key()) @ CustomError::MintAuthorityMismatch
+    )]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: PDA mint authority, validated via seed constraints
+    #[account(seeds = [b"mint-authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        has_one = admin,
+        has_one = mint
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"mint-metadata", mint.key().as_ref()],
+        bump,
+        space = MintMetadata::LEN
+    )]
+    pub metadata: Account<'info, MintMetadata>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateMintMetadata<'info> {
+    #[account(
+        mut,
+        seeds = [b"mint-metadata", metadata.mint.as_ref()],
+        bump = metadata.bump,
+        has_one = update_authority
+    )]
+    pub metadata: Account<'info, MintMetadata>,
+
+    pub update_authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitBlocklist<'info> {
+    #[account(
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        has_one = admin
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"blocklist"],
+        bump,
+        space = Blocklist::LEN
+    )]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(owner: Pubkey)]
+pub struct AddToBlocklist<'info> {
+    #[account(seeds = [b"blocklist"], bump = blocklist.bump, has_one = admin)]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"blocklist-entry", owner.as_ref()],
+        bump,
+        space = BlocklistEntry::LEN
+    )]
+    pub entry: Account<'info, BlocklistEntry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(owner: Pubkey)]
+pub struct RemoveFromBlocklist<'info> {
+    #[account(seeds = [b"blocklist"], bump = blocklist.bump, has_one = admin)]
+    pub blocklist: Account<'info, Blocklist>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [b"blocklist-entry", owner.as_ref()],
+        bump = entry.bump
+    )]
+    pub entry: Account<'info, BlocklistEntry>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FreezeTokenAccount<'info> {
+    #[account(
+        seeds = [b"blocklist"],
+        bump = blocklist.bump,
+        has_one = admin
+    )]
+    pub blocklist: Account<'info, Blocklist>,
+
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::token_program = token_program
+    )]
+    pub token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [b"global-mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: PDA freeze authority, validated via seed constraints
+    #[account(seeds = [b"mint-authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64, root: [u8; 32], max_total_claim: u64, max_num_nodes: u64)]
+pub struct NewDistributor<'info> {
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"distributor", admin.key().as_ref(), &id.to_le_bytes()],
+        bump,
+        space = Distributor::space(max_num_nodes)
+    )]
+    pub distributor: Account<'info, Distributor>,
+
+    /// CHECK: PDA vault authority, validated via seed constraints
+    #[account(seeds = [b"distributor-authority", distributor.key().as_ref()], bump)]
+    pub distributor_authority: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = mint,
+        associated_token::authority = distributor_authority,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = admin,
+        token::token_program = token_program
+    )]
+    pub admin_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(seeds = [b"global-mint"], bump)]
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDistribution<'info> {
+    #[account(
+        mut,
+        seeds = [b"distributor", distributor.admin.as_ref(), &distributor.id.to_le_bytes()],
+        bump = distributor.bump,
+        has_one = vault,
+        has_one = mint
+    )]
+    pub distributor: Account<'info, Distributor>,
+
+    /// CHECK: PDA vault authority, validated via seed constraints
+    #[account(
+        seeds = [b"distributor-authority", distributor.key().as_ref()],
+        bump = distributor.authority_bump
+    )]
+    pub distributor_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = claimant,
+        associated_token::mint = mint,
+        associated_token::authority = claimant,
+        associated_token::token_program = token_program
+    )]
+    pub claimant_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub claimant: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Clawback<'info> {
+    #[account(
+        mut,
+        seeds = [b"distributor", admin.key().as_ref(), &distributor.id.to_le_bytes()],
+        bump = distributor.bump,
+        has_one = admin,
+        has_one = vault,
+        has_one = mint
+    )]
+    pub distributor: Account<'info, Distributor>,
+
+    /// CHECK: PDA vault authority, validated via seed constraints
+    #[account(
+        seeds = [b"distributor-authority", distributor.key().as_ref()],
+        bump = distributor.authority_bump
+    )]
+    pub distributor_authority: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = admin,
+        token::token_program = token_program
+    )]
+    pub admin_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub admin: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64)]
+pub struct CreateVesting<'info> {
+    #[account(
+        init,
+        payer = grantor,
+        seeds = [b"vesting", grantor.key().as_ref(), beneficiary.key().as_ref(), &id.to_le_bytes()],
+        bump,
+        space = VestingAccount::LEN
+    )]
+    pub vesting: Account<'info, VestingAccount>,
+
+    #[account(
+        init,
+        payer = grantor,
+        associated_token::mint = mint,
+        associated_token::authority = vesting,
+        associated_token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = grantor,
+        token::token_program = token_program
+    )]
+    pub grantor_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub beneficiary: SystemAccount<'info>,
+
+    #[account(mut)]
+    pub grantor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimVested<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"vesting",
+            vesting.grantor.as_ref(),
+            beneficiary.key().as_ref(),
+            &vesting.id.to_le_bytes()
+        ],
+        bump = vesting.bump,
+        has_one = beneficiary,
+        has_one = vault,
+        has_one = mint
+    )]
+    pub vesting: Account<'info, VestingAccount>,
+
+    #[account(mut)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = beneficiary,
+        associated_token::mint = mint,
+        associated_token::authority = beneficiary,
+        associated_token::token_program = token_program
+    )]
+    pub beneficiary_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub beneficiary: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct RevokeVesting<'info> {
+    #[account(
+        mut,
+        seeds = [
+            b"vesting",
+            grantor.key().as_ref(),
+            vesting.beneficiary.as_ref(),
+            &vesting.id.to_le_bytes()
+        ],
+        bump = vesting.bump,
+        has_one = grantor,
+        has_one = vault,
+        has_one = mint
+    )]
+    pub vesting: Account<'info, VestingAccount>,
+
+    #[account(mut)]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = mint,
+        token::authority = grantor,
+        token::token_program = token_program
+    )]
+    pub grantor_ata: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    pub grantor: Signer<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitRealm<'info> {
+    #[account(
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        has_one = admin
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"realm"],
+        bump,
+        space = Realm::LEN
+    )]
+    pub realm: Account<'info, Realm>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateRealm<'info> {
+    #[account(mut, seeds = [b"realm"], bump = realm.bump)]
+    pub realm: Account<'info, Realm>,
+
+    // Signed by execute_proposal
+    #[account(seeds = [b"governance-treasury"], bump)]
+    pub governance_treasury: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(
+    proposal_id: u64,
+    title: String,
+    description: String,
+    deadline: i64,
+    instructions: Vec<ProposalInstruction>
+)]
+pub struct CreateProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        init,
+        seeds = [b"proposal", profile.key().as_ref(), &proposal_id.to_le_bytes()],
+        bump,
+        payer = authority,
+        space = Proposal::space(&instructions)
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(seeds = [b"realm"], bump = realm.bump)]
+    pub realm: Account<'info, Realm>,
+
+    #[account(seeds = [b"validator-registry"], bump = registry.bump)]
+    pub registry: Account<'info, ValidatorRegistry>,
+
+    // Needed when the realm sets a minimum proposal weight
+    #[account(
+        seeds = [b"stake-vault", authority.key().as_ref()],
+        bump = stake_vault.bump,
+        constraint = stake_vault.owner == authority.key() @ CustomError::Unauthorized
+    )]
+    pub stake_vault: Option<Account<'info, StakeVault>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FinalizeProposal<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.profile.as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = proposal.status == ProposalStatus::Voting @ CustomError::ProposalNotVoting
+    )]
+    pub proposal: Account<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct CancelProposal<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.profile.as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = profile.owns_proposal(profile.key(), &proposal) @ CustomError::Unauthorized,
+        constraint = proposal.status == ProposalStatus::Voting @ CustomError::ProposalNotVoting
+    )]
+    pub proposal: Account<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct CloseVoteRecord<'info> {
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.profile.as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = proposal.status != ProposalStatus::Voting @ CustomError::ProposalNotFinalized
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    // Seeded from the recorded stake vault, which may since have been migrated away
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"vote", proposal.key().as_ref(), vote_record.stake_vault.as_ref()],
+        bump = vote_record.bump,
+        has_one = proposal,
+        has_one = payer
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+
+    /// CHECK: paid the record's rent, checked against vote_record.payer
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CloseProposal<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"proposal", proposal.profile.as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = profile.owns_proposal(profile.key(), &proposal) @ CustomError::Unauthorized,
+        constraint = proposal.status != ProposalStatus::Voting @ CustomError::ProposalNotFinalized,
+        constraint = proposal.vote_records == 0 @ CustomError::ProposalHasVotes
+    )]
+    pub proposal: Account<'info, Proposal>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteProposal<'info> {
+    pub executor: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.profile.as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = !proposal.executed @ CustomError::AlreadyExecuted
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    /// CHECK: signs the proposal's instructions, holds governance-owned funds
+    #[account(mut, seeds = [b"governance-treasury"], bump)]
+    pub governance_treasury: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VoteOnProposal<'info> {
+    // Profile owner or one of their session keys
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    pub authority: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        has_one = authority,
+        constraint = validator.status == ValidatorStatus::Active @ CustomError::ValidatorInactive,
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority,
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    // Any profile's proposal; the voter's own profile only backs the session check
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.profile.as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = proposal.status == ProposalStatus::Voting @ CustomError::ProposalNotVoting,
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    // One vote per stake, however many validators the authority runs
+    #[account(
+        seeds = [b"stake-vault", authority.key().as_ref()],
+        bump = stake_vault.bump,
+        constraint = stake_vault.owner == authority.key() @ CustomError::Unauthorized,
+    )]
+    pub stake_vault: Account<'info, StakeVault>,
+
+    #[account(
+        init,
+        payer = signer,
+        seeds = [b"vote", proposal.key().as_ref(), stake_vault.key().as_ref()],
+        bump,
+        space = VoteRecord::LEN
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+
+    #[account(
+        seeds = [b"session", profile.key().as_ref(), signer.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Account<'info, SessionKey>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChangeVote<'info> {
+    // Profile owner or one of their session keys
+    pub signer: Signer<'info>,
+
+    pub authority: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority,
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.profile.as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = proposal.status == ProposalStatus::Voting @ CustomError::ProposalNotVoting,
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        seeds = [b"stake-vault", authority.key().as_ref()],
+        bump = stake_vault.bump,
+        constraint = stake_vault.owner == authority.key() @ CustomError::Unauthorized,
+    )]
+    pub stake_vault: Account<'info, StakeVault>,
+
+    #[account(
+        mut,
+        seeds = [b"vote", proposal.key().as_ref(), stake_vault.key().as_ref()],
+        bump = vote_record.bump
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+
+    #[account(
+        seeds = [b"session", profile.key().as_ref(), signer.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Account<'info, SessionKey>>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawVote<'info> {
+    // Profile owner or one of their session keys
+    pub signer: Signer<'info>,
+
+    pub authority: SystemAccount<'info>,
+
+    #[account(
+        seeds = [b"profile", authority.key().as_ref()],
+        bump = profile.bump,
+        has_one = authority,
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        seeds = [b"proposal", proposal.profile.as_ref(), &proposal.id.to_le_bytes()],
+        bump = proposal.bump,
+        constraint = proposal.status == ProposalStatus::Voting @ CustomError::ProposalNotVoting,
+    )]
+    pub proposal: Account<'info, Proposal>,
+
+    #[account(
+        seeds = [b"stake-vault", authority.key().as_ref()],
+        bump = stake_vault.bump,
+        constraint = stake_vault.owner == authority.key() @ CustomError::Unauthorized,
+    )]
+    pub stake_vault: Account<'info, StakeVault>,
+
+    #[account(
+        mut,
+        close = payer,
+        seeds = [b"vote", proposal.key().as_ref(), stake_vault.key().as_ref()],
+        bump = vote_record.bump,
+        has_one = payer
+    )]
+    pub vote_record: Account<'info, VoteRecord>,
+
+    /// CHECK: paid the record's rent, checked against vote_record.payer
+    #[account(mut)]
+    pub payer: UncheckedAccount<'info>,
+
+    #[account(
+        seeds = [b"session", profile.key().as_ref(), signer.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Account<'info, SessionKey>>,
+}
+
+#[derive(Accounts)]
+pub struct StakeTokens<'info> {
+    // Profile owner or one of their session keys
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    pub user: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        constraint = profile.authority == user.key()
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        init_if_needed,
+        seeds = [b"stake-vault", user.key().as_ref()],
+        bump,
+        payer = signer,
+        space = StakeVault::LEN
+    )]
+    pub stake_vault: Account<'info, StakeVault>,
+
+    #[account(
+        mut,
+        constraint = user_ata.owner == user.key(),
+        constraint = user_ata.mint == stake_mint.key(),
+    )]
+    pub user_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = signer,
+        associated_token::mint = stake_mint,
+        associated_token::authority = stake_vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = pool.stake_mint == stake_mint.key(),
+    )]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(
+        seeds = [b"session", profile.key().as_ref(), signer.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Account<'info, SessionKey>>,
+
+    /// CHECK: blocklist entry of the user, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", user.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&stake_mint.key(), &user_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub user_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct UnstakeTokens<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"profile", user.key().as_ref()],
+        bump = profile.bump,
+        constraint = profile.key() == stake_vault.profile
+    )]
+    pub profile: Account<'info, UserProfile>,
+
+    #[account(
+        mut,
+        constraint = stake_vault.owner == user.key(),
+        seeds = [b"stake-vault", user.key().as_ref()],
+        bump
+    )]
+    pub stake_vault: Account<'info, StakeVault>,
+
+    #[account(
+        mut,
+        constraint = user_ata.owner == user.key(),
+        constraint = user_ata.mint == stake_mint.key()
+    )]
+    pub user_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = stake_mint,
+        associated_token::authority = stake_vault,
+        associated_token::token_program = token_program
+    )]
+    pub vault_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(
+        mut,
+        constraint = stake_vault.pool == pool.key()
+    )]
+    pub pool: Account<'info, StakingPool>,
+
+    /// CHECK: blocklist entry of the user, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", user.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&stake_mint.key(), &user_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub user_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimReward<'info> {
+    // Profile owner or one of their session keys
+    #[account(mut)]
+    pub signer: Signer<'info>,
+
+    pub user: SystemAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"stake-vault", user.key().as_ref()],
+        bump,
+        constraint = stake_vault.owner == user.key()
+    )]
+    pub stake_vault: Account<'info, StakeVault>,
+
+    #[account(
+        mut,
+        constraint = user_reward_ata.owner == user.key(),
+        constraint = user_reward_ata.mint == reward_mint.key()
+    )]
+    pub user_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: mint_authority PDA
+    #[account(seeds = [b"mint-authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    // Rewards paid in the global mint count against its supply policy
+    #[account(mut, seeds = [b"mint-config"], bump = mint_config.bump)]
+    pub mint_config: Account<'info, MintConfig>,
+
+    #[account(
+        mut,
+        constraint = stake_vault.pool == pool.key(),
+        constraint = pool.reward_mint == reward_mint.key(),
+        constraint = pool.reward_vault == reward_vault.key()
+    )]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(
+        seeds = [b"session", stake_vault.profile.as_ref(), signer.key().as_ref()],
+        bump = session.bump
+    )]
+    pub session: Option<Account<'info, SessionKey>>,
+
+    /// CHECK: blocklist entry of the user, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", user.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&reward_mint.key(), &user_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub user_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+#[instruction(id: u64, name: String, reward_per_second: u64, lock_period: u64)]
+pub struct InitStakingPool<'info> {
+    #[account(
+        init,
+        payer = authority,
+        seeds = [b"pool", authority.key().as_ref(), &id.to_le_bytes()],
+        bump,
+        space = StakingPool::LEN
+    )]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    // Pools minting the global mint as reward draw on its emission budget
+    #[account(
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        constraint = reward_mint.key() != mint_config.mint
+            || authority.key() == mint_config.admin @ CustomError::Unauthorized
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    // Reward vault ATA (owned by PDA)
+    #[account(
+        init,
+        payer = authority,
+        associated_token::mint = reward_mint,
+        associated_token::authority = reward_vault_authority,
+        associated_token::token_program = token_program
+    )]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+
+    // PDA authority for the reward vault
+    /// CHECK: PDA authority for vault, validated by seeds
+    #[account(
+        seeds = [b"reward-vault", pool.key().as_ref()],
+        bump
+    )]
+    pub reward_vault_authority: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub rent: Sysvar<'info, Rent>,
+}
+
+#[derive(Accounts)]
+pub struct RefillPool<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    // Ensure admin is tranferring from their own ATA (must match reward mint)
+    #[account(
+        mut,
+        constraint = admin_ata.owner == admin.key(),
+        constraint = admin_ata.mint == pool.reward_mint
+    )]
+    pub admin_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub reward_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = reward_vault,
+        constraint = pool.authority == admin.key()
+    )]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(address = pool.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct UpdatePoolConfig<'info> {
+    #[account(mut, has_one = authority)]
+    pub staking_pool: Account<'info, StakingPool>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetValidatorCommission<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        has_one = authority,
+        constraint = validator.pool == Pubkey::default() || validator.pool == pool.key()
+            @ CustomError::ValidatorPoolMismatch
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub pool: Account<'info, StakingPool>,
+}
+
+#[derive(Accounts)]
+pub struct Delegate<'info> {
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator", validator.authority.as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        constraint = validator.status == ValidatorStatus::Active @ CustomError::ValidatorInactive,
+        constraint = validator.pool == pool.key() @ CustomError::ValidatorPoolMismatch
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(
+        mut,
+        constraint = pool.stake_mint == stake_mint.key()
+    )]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(
+        init_if_needed,
+        payer = delegator,
+        seeds = [b"delegation", validator.key().as_ref(), delegator.key().as_ref()],
+        bump,
+        space = Delegation::LEN
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    #[account(
+        mut,
+        token::mint = stake_mint,
+        token::authority = delegator,
+        token::token_program = token_program
+    )]
+    pub delegator_ata: InterfaceAccount<'info, TokenAccount>,
+
+    // Delegated stake sits in an ATA owned by the validator PDA
+    #[account(
+        init_if_needed,
+        payer = delegator,
+        associated_token::mint = stake_mint,
+        associated_token::authority = validator,
+        associated_token::token_program = token_program
+    )]
+    pub validator_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: blocklist entry of the delegator, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", delegator.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&stake_mint.key(), &delegator_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub delegator_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Undelegate<'info> {
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator", validator.authority.as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        constraint = validator.pool == pool.key() @ CustomError::ValidatorPoolMismatch
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(
+        mut,
+        constraint = pool.stake_mint == stake_mint.key()
+    )]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"delegation", validator.key().as_ref(), delegator.key().as_ref()],
+        bump = delegation.bump,
+        has_one = delegator,
+        has_one = validator
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    #[account(
+        mut,
+        token::mint = stake_mint,
+        token::authority = delegator,
+        token::token_program = token_program
+    )]
+    pub delegator_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = stake_mint,
+        associated_token::authority = validator,
+        associated_token::token_program = token_program
+    )]
+    pub validator_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: blocklist entry of the delegator, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", delegator.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&stake_mint.key(), &delegator_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub delegator_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct Redelegate<'info> {
+    #[account(mut)]
+    pub delegator: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = pool.stake_mint == stake_mint.key()
+    )]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"validator",
+            source_validator.authority.as_ref(),
+            &source_validator.id.to_le_bytes()
+        ],
+        bump = source_validator.bump,
+        constraint = source_validator.pool == pool.key() @ CustomError::ValidatorPoolMismatch
+    )]
+    pub source_validator: Account<'info, ValidatorInfo>,
+    #[account(seeds = [b"uptime".as_ref(), &source_validator.id.to_le_bytes()], bump = source_uptime.bump)]
+    pub source_uptime: Account<'info, ValidatorUptime>,
+
+    #[account(
+        mut,
+        seeds = [
+            b"validator",
+            destination_validator.authority.as_ref(),
+            &destination_validator.id.to_le_bytes()
+        ],
+        bump = destination_validator.bump,
+        constraint = destination_validator.key() != source_validator.key() @ CustomError::InvalidRedelegation,
+        constraint = destination_validator.status == ValidatorStatus::Active @ CustomError::ValidatorInactive,
+        constraint = destination_validator.pool == pool.key() @ CustomError::ValidatorPoolMismatch
+    )]
+    pub destination_validator: Account<'info, ValidatorInfo>,
+    #[account(seeds = [b"uptime".as_ref(), &destination_validator.id.to_le_bytes()], bump = destination_uptime.bump)]
+    pub destination_uptime: Account<'info, ValidatorUptime>,
+
+    #[account(
+        mut,
+        seeds = [b"delegation", source_validator.key().as_ref(), delegator.key().as_ref()],
+        bump = source_delegation.bump,
+        has_one = delegator
+    )]
+    pub source_delegation: Account<'info, Delegation>,
+
+    #[account(
+        init_if_needed,
+        payer = delegator,
+        seeds = [b"delegation", destination_validator.key().as_ref(), delegator.key().as_ref()],
+        bump,
+        space = Delegation::LEN
+    )]
+    pub destination_delegation: Account<'info, Delegation>,
+
+    #[account(
+        mut,
+        associated_token::mint = stake_mint,
+        associated_token::authority = source_validator,
+        associated_token::token_program = token_program
+    )]
+    pub source_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        init_if_needed,
+        payer = delegator,
+        associated_token::mint = stake_mint,
+        associated_token::authority = destination_validator,
+        associated_token::token_program = token_program
+    )]
+    pub destination_vault: InterfaceAccount<'info, TokenAccount>,
+
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: blocklist entry of the delegator, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", delegator.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&stake_mint.key(), &delegator_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub delegator_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimDelegationRewards<'info> {
+    pub delegator: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator", validator.authority.as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        constraint = validator.pool == pool.key() @ CustomError::ValidatorPoolMismatch
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(mut)]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(
+        mut,
+        seeds = [b"delegation", validator.key().as_ref(), delegator.key().as_ref()],
+        bump = delegation.bump,
+        has_one = delegator,
+        has_one = validator
+    )]
+    pub delegation: Account<'info, Delegation>,
+
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = delegator,
+        token::token_program = token_program
+    )]
+    pub delegator_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, address = pool.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: mint_authority PDA
+    #[account(seeds = [b"mint-authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"mint-config"], bump = mint_config.bump)]
+    pub mint_config: Account<'info, MintConfig>,
+
+    /// CHECK: blocklist entry of the delegator, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", delegator.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&reward_mint.key(), &delegator_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub delegator_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct ClaimCommission<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        has_one = authority,
+        constraint = validator.pool == pool.key() @ CustomError::ValidatorPoolMismatch
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(mut)]
+    pub pool: Account<'info, StakingPool>,
+
+    #[account(
+        mut,
+        token::mint = reward_mint,
+        token::authority = authority,
+        token::token_program = token_program
+    )]
+    pub authority_reward_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, address = pool.reward_mint)]
+    pub reward_mint: InterfaceAccount<'info, Mint>,
+
+    /// CHECK: mint_authority PDA
+    #[account(seeds = [b"mint-authority"], bump)]
+    pub mint_authority: UncheckedAccount<'info>,
+
+    #[account(mut, seeds = [b"mint-config"], bump = mint_config.bump)]
+    pub mint_config: Account<'info, MintConfig>,
+
+    /// CHECK: blocklist entry of the validator authority, only exists while they are blocked
+    #[account(
+        seeds = [b"blocklist-entry", authority.key().as_ref()],
+        bump,
+        constraint = BlocklistEntry::allows(&reward_mint.key(), &authority_blocklist_entry) @ CustomError::Blocklisted
+    )]
+    pub authority_blocklist_entry: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct InitValidatorConfig<'info> {
+    #[account(
+        seeds = [b"mint-config"],
+        bump = mint_config.bump,
+        has_one = admin
+    )]
+    pub mint_config: Account<'info, MintConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        seeds = [b"validator-config"],
+        bump,
+        space = ValidatorConfig::LEN
+    )]
+    pub validator_config: Account<'info, ValidatorConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateValidatorConfig<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator-config"],
+        bump = validator_config.bump,
+        has_one = admin
+    )]
+    pub validator_config: Account<'info, ValidatorConfig>,
+
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(evidence_hash: [u8; 32])]
+pub struct SlashValidator<'info> {
+    #[account(
+        seeds = [b"validator-config"],
+        bump = validator_config.bump,
+        has_one = admin
+    )]
+    pub validator_config: Account<'info, ValidatorConfig>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator", validator.authority.as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(
+        init,
+        payer = admin,
+        // Keyed by id, which survives authority migrations, so evidence slashes once
+        seeds = [b"slash".as_ref(), &validator.id.to_le_bytes(), evidence_hash.as_ref()],
+        bump,
+        space = SlashEvent::LEN
+    )]
+    pub slash_event: Account<'info, SlashEvent>,
+
+    // Required while the validator holds delegated stake
+    #[account(mut, address = validator.pool @ CustomError::ValidatorPoolMismatch)]
+    pub pool: Option<Account<'info, StakingPool>>,
+
+    #[account(
+        mut,
+        associated_token::mint = stake_mint,
+        associated_token::authority = validator,
+        associated_token::token_program = token_program
+    )]
+    pub validator_vault: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    /// CHECK: the operator's own stake vault, slashed whenever it exists so it can't be
+    /// left out; deserialized in the handler
+    #[account(mut, seeds = [b"stake-vault", validator.authority.as_ref()], bump)]
+    pub stake_vault: UncheckedAccount<'info>,
+
+    // Pool of the operator's stake, when it isn't the delegation pool above
+    #[account(mut)]
+    pub stake_pool: Option<Account<'info, StakingPool>>,
+
+    #[account(
+        mut,
+        token::mint = stake_mint,
+        token::token_program = token_program
+    )]
+    pub stake_vault_ata: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut)]
+    pub stake_mint: InterfaceAccount<'info, Mint>,
+
+    // Receives slashed tokens; omitted when the config burns them
+    #[account(
+        mut,
+        address = validator_config.slash_destination @ CustomError::InvalidRecipientAccount,
+        token::mint = stake_mint,
+        token::token_program = token_program
+    )]
+    pub slash_destination: Option<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct Unjail<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        has_one = authority
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump,
+        has_one = authority
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    pub authority: Signer<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()],
+        bump = uptime.bump
+    )]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    /// CHECK: the operator's stake vault, read only if it exists
+    #[account(seeds = [b"stake-vault", authority.key().as_ref()], bump)]
+    pub stake_vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct CrankUptime<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator", validator.authority.as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    #[account(seeds = [b"validator-config"], bump = validator_config.bump)]
+    pub validator_config: Account<'info, ValidatorConfig>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct SyncVotingWeight<'info> {
+    #[account(
+        mut,
+        seeds = [b"validator", validator.authority.as_ref(), &validator.id.to_le_bytes()],
+        bump = validator.bump
+    )]
+    pub validator: Account<'info, ValidatorInfo>,
+
+    #[account(seeds = [b"uptime".as_ref(), &validator.id.to_le_bytes()], bump = uptime.bump)]
+    pub uptime: Account<'info, ValidatorUptime>,
+
+    /// CHECK: the operator's stake vault, read only if it exists
+    #[account(seeds = [b"stake-vault", validator.authority.as_ref()], bump)]
+    pub stake_vault: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [b"validator-registry"],
+        bump = registry.bump
+    )]
+    pub registry: Account<'info, ValidatorRegistry>,
+}
+
+#[derive(Accounts)]
+pub struct InitEscrow<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    #[account(
+        mut,
+        constraint = initializer_deposit_token_account.owner == initializer.key(),
+        constraint = initializer_deposit_token_account.mint == mint_offered.key()
+    )]
+    pub initializer_deposit_token_account: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub payer: Signer<'info>, // This type will check that the Signer account signed the transaction; it checks that the signature matches the public key of the account.
+    pub mint_offered: InterfaceAccount<'info, Mint>,
 
-    pub token_program: Program<'info, Token>,
-    pub rent: Sysvar<'info, Rent>,
-    pub system_program: Program<'info, System>,
-}
+    #[account(mut)]
+    pub mint_expected: InterfaceAccount<'info, Mint>,
 
-#[derive(Accounts)]
-#[instruction(id: u64, name: String)]
-pub struct InitValidator<'info> {
     #[account(
         init,
-        seeds = [b"validator", authority.key().as_ref(), &id.to_le_bytes()],
+        payer = initializer,
+        seeds = [b"escrow", initializer.key().as_ref()],
         bump,
-        payer = authority,
-        space = ValidatorInfo::LEN
+        space = Escrow::LEN
     )]
-    pub validator: Account<'info, ValidatorInfo>,
-
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    pub escrow: Account<'info, Escrow>,
 
+    // This vault account is PDA ATA that holds initializers offered tokens
     #[account(
-        seeds = [b"profile", authority.key().as_ref()],
-        bump = profile.bump,
-        has_one = authority
+        init,
+        payer = initializer,
+        associated_token::mint = mint_offered,
+        associated_token::authority = vault_authority,
+        associated_token::token_program = token_program
     )]
-    pub profile: Account<'info, UserProfile>,
+    pub vault_amount: InterfaceAccount<'info, TokenAccount>,
 
+    /// CHECK: PDA used to own vault_account
     #[account(
-        init,
-        payer = authority,
-        associated_token::mint = mint,
-        associated_token::authority = authority
+        seeds = [b"vault-authority", initializer.key().as_ref()],
+        bump
     )]
-    pub validator_ata: Account<'info, TokenAccount>,
-
-    /// CHECK: Mint authority PDA
-    #[account(seeds = [b"mint-authority"], bump)]
-    pub mint_authority: UncheckedAccount<'info>,
-
-    #[account(mut, seeds = [b"global-mint"], bump)]
-    pub mint: Account<'info, Mint>,
+    pub vault_authority: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct UpdateValidator<'info> {
+pub struct FulfillEscrow<'info> {
+    #[account(mut)]
+    pub taker: Signer<'info>,
+
+    #[account(mut, constraint = taker_payment_ata.owner == taker.key())]
+    pub taker_payment_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut, constraint = initializer_receive_ata.owner == escrow.initializer)]
+    pub initializer_receive_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub vault_amount: InterfaceAccount<'info, TokenAccount>,
+
     #[account(
-        mut,
-        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
-        bump = validator.bump,
-        has_one = authority,
-        has_one = profile
+        seeds = [b"vault-authority", escrow.initializer.as_ref()],
+        bump
     )]
-    pub validator: Account<'info, ValidatorInfo>,
+    /// CHECK: Vault authority PDA for signing transfer
+    pub vault_authority: UncheckedAccount<'info>,
 
-    pub authority: Signer<'info>,
+    #[account(mut)]
+    pub taker_receive_ata: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        seeds = [b"profile", authority.key().as_ref()],
-        bump = profile.bump,
-        has_one = authority
+        mut,
+        seeds = [b"escrow", escrow.initializer.as_ref()],
+        bump,
+        has_one = vault_amount @ CustomError::Unauthorized,
+        constraint = !escrow.is_fulfilled @ CustomError::AlreadyFulfilled
     )]
-    pub profile: Account<'info, UserProfile>,
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(address = escrow.mint_offered)]
+    pub mint_offered: InterfaceAccount<'info, Mint>,
+
+    #[account(address = escrow.mint_expected)]
+    pub mint_expected: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct CloseValidator<'info> {
+pub struct CancelEscrow<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+
+    #[account(mut)]
+    pub initializer_receive_ata: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub vault_amount: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(
+        seeds = [b"vault-authority", initializer.key().as_ref()],
+        bump
+    )]
+    /// CHECK: PDA signer
+    pub vault_authority: UncheckedAccount<'info>,
+
     #[account(
         mut,
-        close = authority,
-        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
-        bump = validator.bump,
-        has_one = authority,
-        has_one = profile
+        seeds = [b"escrow", initializer.key().as_ref()],
+        bump = escrow.bump,
+        constraint = !escrow.is_fulfilled @ CustomError::AlreadyFulfilled,
+        has_one = initializer @ CustomError::Unauthorized
     )]
-    pub validator: Account<'info, ValidatorInfo>,
+    pub escrow: Account<'info, Escrow>,
+
+    #[account(address = escrow.mint_offered)]
+    pub mint_offered: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
 
+#[derive(Accounts)]
+pub struct Swap<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub user_input_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_output_token_account: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub input_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub output_vault: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        seeds = [b"profile", authority.key().as_ref()],
-        bump = profile.bump,
-        has_one = authority
+        seeds = [b"vault", pool.key().as_ref(), output_token_mint.key().as_ref()],
+        bump
     )]
-    pub profile: Account<'info, UserProfile>,
+    /// CHECK: PDA signer for output_token_mint auth
+    pub vault_authority: UncheckedAccount<'info>,
+
+    #[account(constraint = input_token_mint.key() == input_vault.mint)]
+    pub input_token_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub output_token_mint: InterfaceAccount<'info, Mint>, // Seeds the vault authority PDA
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct TransferTokens<'info> {
+pub struct AddLiquidity<'info> {
     #[account(mut)]
-    pub sender: Signer<'info>,
+    pub user: Signer<'info>,
 
     #[account(mut)]
-    pub from: Account<'info, TokenAccount>, // SENDERS ATA
+    pub user_token_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_token_b: InterfaceAccount<'info, TokenAccount>,
     #[account(mut)]
-    pub to: Account<'info, TokenAccount>, // RECEIVERS ATA
+    pub user_lp_token_account: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
-}
+    #[account(mut)]
+    pub token_a_vault: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub token_b_vault: InterfaceAccount<'info, TokenAccount>,
 
-impl<'info> TransferTokens<'info> {
-    pub fn into_transfer_context(&self) -> CpiContext<'_, '_, '_, 'info, Transfer<'info>> {
-        let cpi_accounts = Transfer {
-            from: self.from.to_account_info(),
-            to: self.to.to_account_info(),
-            authority: self.sender.to_account_info(),
-        };
-        CpiContext::new(self.token_program.to_account_info(), cpi_accounts)
-    }
-}
+    #[account(address = pool.token_a_mint)]
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = pool.token_b_mint)]
+    pub token_b_mint: InterfaceAccount<'info, Mint>,
 
-#[derive(Accounts)]
-pub struct BurnTokens<'info> {
     #[account(mut)]
-    pub owner: Signer<'info>,
+    pub lp_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub pool: Account<'info, Pool>,
 
     #[account(
-        mut,
-        constraint = owner_ata.owner == owner.key(),
-        constraint = owner_ata.mint == mint.key()
+        seeds = [b"vault-authority", pool.key().as_ref()],
+        bump
     )]
-    pub owner_ata: Account<'info, TokenAccount>,
+    /// CHECK: PDA for mint authority
+    pub vault_authority: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveLiquidity<'info> {
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    #[account(mut)]
+    pub user_lp_token: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_token_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_token_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_a_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub token_b_vault: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pool.token_a_mint)]
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = pool.token_b_mint)]
+    pub token_b_mint: InterfaceAccount<'info, Mint>,
+
+    #[account(mut)]
+    pub lp_mint: InterfaceAccount<'info, Mint>,
 
     #[account(mut)]
-    pub mint: Account<'info, Mint>,
+    pub pool: Account<'info, Pool>,
+
+    #[account(
+        seeds = [b"vault-authority", pool.key().as_ref()],
+        bump
+    )]
+    /// CHECK: PDA used as vault authority
+    pub vault_authority: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct ReassignMintAuthority<'info> {
+#[instruction(owners: Vec<Pubkey>)]
+pub struct CreateMultisig<'info> {
     #[account(
-        mut,
-        seeds = [b"global-mint"],
-        bump
+        init,
+        seeds = [b"multisig", payer.key().as_ref()],
+        bump,
+        payer = payer,
+        space = Multisig::LEN
     )]
-    pub mint: Account<'info, Mint>,
+    pub multisig: Account<'info, Multisig>,
 
-    /// CHECK: PDA mint authority (not enforced here, signer seeds used instead)
-    #[account(seeds = [b"mint-authority"], bump)]
-    pub mint_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-#[instruction(proposal_id: u64)]
-pub struct CreateProposal<'info> {
+#[instruction(tx_nonce: u8)]
+pub struct ProposeTransaction<'info> {
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+
     #[account(
         mut,
-        seeds = [b"profile", authority.key().as_ref()],
-        bump = profile.bump,
-        has_one = authority
+        seeds = [b"multisig", proposer.key().as_ref()],
+        bump
     )]
-    pub profile: Account<'info, UserProfile>,
+    pub multisig: Account<'info, Multisig>,
 
     #[account(
         init,
-        seeds = [b"proposal", profile.key().as_ref(), &proposal_id.to_le_bytes()],
-        bump,
-        payer = authority,
-        space = Proposal::LEN
+        payer = proposer,
+        space = Transaction::LEN,
+        seeds = [b"tx", multisig.key().as_ref(), &[tx_nonce]],
+        bump
     )]
-    pub proposal: Account<'info, Proposal>,
-
-    #[account(mut)]
-    pub authority: Signer<'info>,
+    pub tx: Account<'info, Transaction>,
 
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct VoteOnProposal<'info> {
+pub struct ApproveTransaction<'info> {
     #[account(mut)]
-    pub authority: Signer<'info>,
+    pub signer: Signer<'info>,
 
     #[account(
-        seeds = [b"validator", authority.key().as_ref(), &validator.id.to_le_bytes()],
-        bump = validator.bump,
-        has_one = authority,
+        seeds = [b"multisig", signer.key().as_ref()],
+        bump
     )]
-    pub validator: Account<'info, ValidatorInfo>,
+    pub multisig: Account<'info, Multisig>,
 
     #[account(
-        seeds = [b"profile", authority.key().as_ref()],
-        bump = profile.bump,
-        has_one = authority,
+        mut,
+        seeds = [b"tx", multisig.key().as_ref()],
+        bump,
+        constraint = !tx.did_execute @ CustomError::AlreadyExecuted
     )]
-    pub profile: Account<'info, UserProfile>,
+    pub tx: Account<'info, Transaction>,
+}
+
+#[derive(Accounts)]
+#[instruction(tx_nonce: u8)]
+pub struct ExecuteTransaction<'info> {
+    #[account(mut)]
+    pub executor: Signer<'info>,
 
     #[account(
-        mut,
-        seeds = [b"proposal", profile.key().as_ref(), &proposal.id.to_le_bytes()],
-        bump = proposal.bump,
+        seeds = [b"multisig", executor.key().as_ref()],
+        bump
     )]
-    pub proposal: Account<'info, Proposal>,
+    pub multisig: Account<'info, Multisig>,
 
     #[account(
-        init,
-        payer = authority,
-        seeds = [b"vote", proposal.key().as_ref(), validator.key().as_ref()],
+        mut,
+        seeds = [b"tx", multisig.key().as_ref(), &[tx_nonce]],
         bump,
-        space = VoteRecord::LEN
+        constraint = !tx.did_execute @ CustomError::AlreadyExecuted,
+        constraint = tx.multisig == multisig.key() @ CustomError::Unauthorized,
     )]
-    pub vote_record: Account<'info, VoteRecord>,
-
-    pub system_program: Program<'info, System>,
+    pub tx: Account<'info, Transaction>,
 }
 
 #[derive(Accounts)]
-pub struct StakeTokens<'info> {
+#[instruction(tick_spacing: u16)]
+pub struct InitPoolClmm<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub payer: Signer<'info>,
 
+    /// Pool state account (PDA)
     #[account(
-        mut,
-        constraint = profile.authority == user.key()
+        init,
+        payer = payer,
+        seeds = [b"pool-clmm", token_a_mint.key().as_ref(), token_b_mint.key().as_ref()],
+        bump,
+        space = PoolClmm::LEN
     )]
-    pub profile: Account<'info, UserProfile>,
+    pub pool_clmm: Account<'info, PoolClmm>,
 
+    /// Token A and B mints
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    pub token_b_mint: InterfaceAccount<'info, Mint>,
+
+    /// Vaults to hold A/B liquidity
     #[account(
-        init_if_needed,
-        seeds = [b"stake-vault", user.key().as_ref()],
-        bump,
-        payer = user,
-        space = StakeVault::LEN
+        init,
+        payer = payer,
+        token::mint = token_a_mint,
+        token::authority = pool_signer,
+        token::token_program = token_program
     )]
-    pub stake_vault: Account<'info, StakeVault>,
+    pub vault_a: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        mut,
-        constraint = user_ata.owner == user.key(),
-        constraint = user_ata.mint == stake_mint.key(),
+        init,
+        payer = payer,
+        token::mint = token_b_mint,
+        token::authority = pool_signer,
+        token::token_program = token_program
     )]
-    pub user_ata: Account<'info, TokenAccount>,
+    pub vault_b: InterfaceAccount<'info, TokenAccount>,
 
+    /// PDA that signs for the vaults
     #[account(
-        init_if_needed,
-        payer = user,
-        associated_token::mint = stake_mint,
-        associated_token::authority = stake_vault,
+        seeds = [b"signer", pool_clmm.key().as_ref()],
+        bump
     )]
-    pub vault_ata: Account<'info, TokenAccount>,
+    /// CHECK: Used only as a PDA signer
+    pub pool_signer: UncheckedAccount<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
 
+#[derive(Accounts)]
+#[instruction(tick_index: i32)]
+pub struct InitTick<'info> {
     #[account(mut)]
-    pub stake_mint: Account<'info, Mint>,
+    pub payer: Signer<'info>,
+
+    /// The CLMM pool this tick belongs to
+    pub pool_clmm: Account<'info, PoolClmm>,
 
     #[account(
-        mut,
-        constraint = pool.stake_mint == stake_mint.key(),
+        init,
+        payer = payer,
+        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_index.to_le_bytes()],
+        bump,
+        space = Tick::LEN
     )]
-    pub pool: Account<'info, StakingPool>,
+    pub tick: Account<'info, Tick>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct UnstakeTokens<'info> {
+pub struct OpenPosition<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub owner: Signer<'info>,
 
-    #[account(
-        mut,
-        constraint = stake_vault.owner == user.key(),
-        seeds = [b"stake-vault", user.key().as_ref()],
-        bump
-    )]
-    pub stake_vault: Account<'info, StakeVault>,
+    #[account(mut)]
+    pub pool_clmm: Account<'info, PoolClmm>,
 
-    #[account(
-        mut,
-        constraint = user_ata.owner == user.key(),
-        constraint = user_ata.mint == stake_mint.key()
-    )]
-    pub user_ata: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_token_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_token_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub vault_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub vault_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        mut,
-        associated_token::mint = stake_mint,
-        associated_token::authority = stake_vault
+        seeds = [b"signer", pool_clmm.key().as_ref()],
+        bump = pool_clmm.signer_bump
     )]
-    pub vault_ata: Account<'info, TokenAccount>,
+    /// CHECK: Only used for CPI signing
+    pub pool_signer: UncheckedAccount<'info>,
 
     #[account(mut)]
-    pub stake_mint: Account<'info, Mint>,
+    tick_lower: Account<'info, Tick>,
+    #[account(mut)]
+    tick_upper: Account<'info, Tick>,
 
     #[account(
-        mut,
-        constraint = stake_vault.pool == pool.key()
+        init,
+        payer = owner,
+        seeds = [
+            b"position",
+            owner.key().as_ref(),
+            pool_clmm.key().as_ref(),
+            &tick_lower.tick_index.to_le_bytes(),
+            &tick_upper.tick_index.to_le_bytes(),
+        ],
+        bump,
+        space = Position::LEN
     )]
-    pub pool: Account<'info, StakingPool>,
+    pub user_position: Account<'info, Position>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
+    #[account(address = pool_clmm.token_a_mint)]
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = pool_clmm.token_b_mint)]
+    pub token_b_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
 }
 
 #[derive(Accounts)]
-pub struct ClaimReward<'info> {
+pub struct DecreaseLiquidity<'info> {
     #[account(mut)]
-    pub user: Signer<'info>,
+    pub owner: Signer<'info>,
 
+    /// The pool for which the position was opened
     #[account(
         mut,
-        seeds = [b"stake-vault", user.key().as_ref()],
-        bump,
-        constraint = stake_vault.owner == user.key()
+        has_one = vault_a,
+        has_one = vault_b
     )]
-    pub stake_vault: Account<'info, StakeVault>,
+    pub pool_clmm: Account<'info, PoolClmm>,
 
+    /// Position owned by the user
     #[account(
         mut,
-        constraint = user_reward_ata.owner == user.key(),
-        constraint = user_reward_ata.mint == reward_mint.key()
+        seeds = [
+            b"position",
+            owner.key().as_ref(),
+            pool_clmm.key().as_ref(),
+            &tick_lower.tick_index.to_le_bytes(),
+            &tick_upper.tick_index.to_le_bytes(),
+        ],
+        bump,
+        has_one = owner,
+        has_one = pool_clmm
     )]
-    pub user_reward_ata: Account<'info, TokenAccount>,
-
-    #[account(mut)]
-    pub reward_mint: Account<'info, Mint>,
-
-    #[account(mut)]
-    pub reward_vault: Account<'info, TokenAccount>,
-
-    /// CHECK: mint_authority PDA
-    #[account(seeds = [b"mint-authority"], bump)]
-    pub mint_authority: UncheckedAccount<'info>,
+    pub user_position: Account<'info, Position>,
 
+    /// Lower tick account
     #[account(
         mut,
-        constraint = stake_vault.pool == pool.key(),
-        constraint = pool.reward_mint == reward_mint.key(),
-        constraint = pool.reward_vault == reward_vault.key()
+        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_lower.tick_index.to_le_bytes()],
+        bump
     )]
-    pub pool: Account<'info, StakingPool>,
-
-    pub token_program: Program<'info, Token>,
-}
+    pub tick_lower: Account<'info, Tick>,
 
-#[derive(Accounts)]
-#[instruction(id: u64, name: String, reward_per_second: u64, lock_period: u64)]
-pub struct InitStakingPool<'info> {
+    /// Upper tick account
     #[account(
-        init,
-        payer = authority,
-        seeds = [b"pool", authority.key().as_ref(), &id.to_le_bytes()],
-        bump,
-        space = StakingPool::LEN
+        mut,
+        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_upper.tick_index.to_le_bytes()],
+        bump
     )]
-    pub pool: Account<'info, StakingPool>,
+    pub tick_upper: Account<'info, Tick>,
 
+    /// Vault A (Token A liquidity vault)
     #[account(mut)]
-    pub authority: Signer<'info>,
-
-    pub stake_mint: Account<'info, Mint>,
-    pub reward_mint: Account<'info, Mint>,
+    pub vault_a: InterfaceAccount<'info, TokenAccount>,
 
-    // Reward vault ATA (owned by PDA)
-    #[account(
-        init,
-        payer = authority,
-        associated_token::mint = reward_mint,
-        associated_token::authority = reward_vault_authority
-    )]
-    pub reward_vault: Account<'info, TokenAccount>,
+    /// Vault B (Token B liquidity vault)
+    #[account(mut)]
+    pub vault_b: InterfaceAccount<'info, TokenAccount>,
 
-    // PDA authority for the reward vault
-    /// CHECK: PDA authority for vault, validated by seeds
+    /// Vault signer PDA (pool auth)
     #[account(
-        seeds = [b"reward-vault", pool.key().as_ref()],
+        seeds = [b"signer", pool_clmm.key().as_ref()],
         bump
     )]
-    pub reward_vault_authority: UncheckedAccount<'info>,
+    /// CHECK: PDA auth only
+    pub pool_signer: UncheckedAccount<'info>,
 
-    pub system_program: Program<'info, System>,
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub rent: Sysvar<'info, Rent>,
+    /// Users receiving token accounts
+    #[account(mut)]
+    pub user_token_a: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user_token_b: InterfaceAccount<'info, TokenAccount>,
+
+    #[account(address = pool_clmm.token_a_mint)]
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = pool_clmm.token_b_mint)]
+    pub token_b_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct RefillPool<'info> {
+pub struct CollectFees<'info> {
     #[account(mut)]
-    pub admin: Signer<'info>,
+    pub owner: Signer<'info>,
 
-    // Ensure admin is tranferring from their own ATA (must match reward mint)
+    /// Pool for which fees are collected
     #[account(
         mut,
-        constraint = admin_ata.owner == admin.key(),
-        constraint = admin_ata.mint == pool.reward_mint
+        has_one = vault_a,
+        has_one = vault_b
     )]
-    pub admin_ata: Account<'info, TokenAccount>,
+    pub pool_clmm: Account<'info, PoolClmm>,
 
-    #[account(mut)]
-    pub reward_vault: Account<'info, TokenAccount>,
+    /// Users position account
     #[account(
         mut,
-        has_one = reward_vault,
-        constraint = pool.authority == admin.key()
+        seeds = [
+            b"position",
+            owner.key().as_ref(),
+            pool_clmm.key().as_ref(),
+            &tick_lower.tick_index.to_le_bytes(),
+            &tick_upper.tick_index.to_le_bytes(),
+        ],
+        bump,
+        has_one = owner,
+        has_one = pool_clmm
     )]
-    pub pool: Account<'info, StakingPool>,
-
-    pub token_program: Program<'info, Token>,
-}
-
-#[derive(Accounts)]
-pub struct UpdatePoolConfig<'info> {
-    #[account(mut, has_one = authority)]
-    pub staking_pool: Account<'info, StakingPool>,
-    pub authority: Signer<'info>,
-}
-
-#[derive(Accounts)]
-pub struct InitEscrow<'info> {
-    #[account(mut)]
-    pub initializer: Signer<'info>,
+    pub user_position: Account<'info, Position>,
 
+    /// Lower and upper tick accounts (used for future fee tracking logic)
     #[account(
-        mut,
-        constraint = initializer_deposit_token_account.owner == initializer.key(),
-        constraint = initializer_deposit_token_account.mint == mint_offered.key()
+        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_lower.tick_index.to_le_bytes()],
+        bump
+    )]
+    pub tick_lower: Account<'info, Tick>,
+
+    #[account(
+        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_upper.tick_index.to_le_bytes()],
+        bump
     )]
-    pub initializer_deposit_token_account: Account<'info, TokenAccount>,
+    pub tick_upper: Account<'info, Tick>,
 
+    /// Vault that hold fees
     #[account(mut)]
-    pub mint_offered: Account<'info, Mint>,
+    pub vault_a: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub mint_expected: Account<'info, Mint>,
+    pub vault_b: InterfaceAccount<'info, TokenAccount>,
 
+    /// PDA signer that controls the vaults
     #[account(
-        init,
-        payer = initializer,
-        seeds = [b"escrow", initializer.key().as_ref()],
-        bump,
-        space = Escrow::LEN
+        seeds = [b"signer", pool_clmm.key().as_ref()],
+        bump
     )]
-    pub escrow: Account<'info, Escrow>,
+    /// CHECK: PDA signer only
+    pub pool_signer: UncheckedAccount<'info>,
 
-    // This vault account is PDA ATA that holds initializers offered tokens
-    #[account(
-        init,
-        payer = initializer,
-        associated_token::mint = mint_offered,
-        associated_token::authority = vault_authority
-    )]
-    pub vault_amount: Account<'info, TokenAccount>,
+    /// User receiving accounts
+    #[account(mut)]
+    pub user_token_a: InterfaceAccount<'info, TokenAccount>,
 
-    /// CHECK: PDA used to own vault_account
-    #[account(
-        seeds = [b"vault-authority", initializer.key().as_ref()],
-        bump
-    )]
-    pub vault_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub user_token_b: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
-    pub associated_token_program: Program<'info, AssociatedToken>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+    #[account(address = pool_clmm.token_a_mint)]
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = pool_clmm.token_b_mint)]
+    pub token_b_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
 #[derive(Accounts)]
-pub struct FulfillEscrow<'info> {
+pub struct SwapClmm<'info> {
     #[account(mut)]
-    pub taker: Signer<'info>,
+    pub user: Signer<'info>,
 
-    #[account(mut, constraint = taker_payment_ata.owner == taker.key())]
-    pub taker_payment_ata: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub pool_clmm: Account<'info, PoolClmm>,
 
-    #[account(mut, constraint = initializer_receive_ata.owner == escrow.initializer)]
-    pub initializer_receive_ata: Account<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_source_token: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub user_destination_token: InterfaceAccount<'info, TokenAccount>,
 
     #[account(mut)]
-    pub vault_amount: Account<'info, TokenAccount>,
+    pub vault_source: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub vault_destination: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        seeds = [b"vault-authority", escrow.initializer.as_ref()],
+        seeds = [b"signer", pool_clmm.key().as_ref()],
         bump
     )]
-    /// CHECK: Vault authority PDA for signing transfer
-    pub vault_authority: UncheckedAccount<'info>,
+    /// CHECK: PDA auth only
+    pub pool_signer: UncheckedAccount<'info>,
+
+    #[account(constraint = source_mint.key() == vault_source.mint)]
+    pub source_mint: InterfaceAccount<'info, Mint>,
+    #[account(constraint = destination_mint.key() == vault_destination.mint)]
+    pub destination_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
 
+#[derive(Accounts)]
+#[instruction(tick_lower: i32, tick_upper: i32)]
+pub struct ClosePosition<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    /// Pool the position belongs to
     #[account(mut)]
-    pub taker_receive_ata: Account<'info, TokenAccount>,
+    pub pool_clmm: Account<'info, PoolClmm>,
 
+    /// Users position to close
     #[account(
         mut,
-        seeds = [b"escrow", escrow.initializer.as_ref()],
+        close = owner,
+        seeds = [
+            b"position",
+            owner.key().as_ref(),
+            pool_clmm.key().as_ref(),
+            &tick_lower.to_le_bytes(),
+            &tick_upper.to_le_bytes()
+        ],
         bump,
-        has_one = vault_amount @ CustomError::Unauthorized,
-        constraint = !escrow.is_fulfilled @ CustomError::AlreadyFulfilled
+        has_one = owner,
+        has_one = pool_clmm
     )]
-    pub escrow: Account<'info, Escrow>,
-
-    pub token_program: Program<'info, Token>,
+    pub user_position: Account<'info, Position>,
 }
 
 #[derive(Accounts)]
-pub struct CancelEscrow<'info> {
+pub struct CollectProtocolFees<'info> {
     #[account(mut)]
-    pub initializer: Signer<'info>,
+    pub authority: Signer<'info>,
 
-    #[account(mut)]
-    pub initializer_receive_ata: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        has_one = vault_a,
+        has_one = vault_b,
+        has_one = authority
+    )]
+    pub pool_clmm: Account<'info, PoolClmm>,
 
     #[account(mut)]
-    pub vault_amount: Account<'info, TokenAccount>,
+    pub vault_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub vault_b: InterfaceAccount<'info, TokenAccount>,
 
     #[account(
-        seeds = [b"vault-authority", initializer.key().as_ref()],
-        bump
+        seeds = [b"signer", pool_clmm.key().as_ref()],
+        bump = pool_clmm.signer_bump
     )]
-    /// CHECK: PDA signer
-    pub vault_authority: UncheckedAccount<'info>,
+    /// CHECK: PDA signer for vault auth
+    pub pool_signer: UncheckedAccount<'info>,
 
-    #[account(
-        mut,
-        seeds = [b"escrow", initializer.key().as_ref()],
-        bump = escrow.bump,
-        constraint = !escrow.is_fulfilled @ CustomError::AlreadyFulfilled,
-        has_one = initializer @ CustomError::Unauthorized
-    )]
-    pub escrow: Account<'info, Escrow>,
+    #[account(mut)]
+    pub protocol_fee_recipient_a: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub protocol_fee_recipient_b: InterfaceAccount<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(address = pool_clmm.token_a_mint)]
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    #[account(address = pool_clmm.token_b_mint)]
+    pub token_b_mint: InterfaceAccount<'info, Mint>,
+
+    pub token_program: Interface<'info, TokenInterface>,
 }
 
-#[derive(Accounts)]
-pub struct Swap<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+// ----------------- ACCOUNT STRUCTS ---------------------
 
-    #[account(mut)]
-    pub user_input_token_account: Account<'info, TokenAccount>,
+#[account]
+pub struct UserProfile {
+    pub authority: Pubkey,          // 32
+    pub name: String,               // 4 (length prefix) + max characters
+    pub bio: String,                // 4 + N
+    pub avatar_uri: String,         // 4 + N
+    pub website: String,            // 4 + N
+    pub handle: String,             // claimed @handle, empty if none
+    pub validator_count: u32,       // validators created by this profile
+    pub proposal_count: u32,        // proposals created by this profile
+    pub active_stakes: u32,         // stake vaults currently holding tokens
+    pub guardians: Vec<Pubkey>,     // social recovery set, 4 + 32 * MAX_GUARDIANS
+    pub guardian_threshold: u8,     // approvals needed to start a recovery
+    pub pending_authority: Pubkey,  // rotation target, default if none pending
+    pub recovery_approvals: u8,     // bitmap indexed like `guardians`
+    pub rotation_eta: i64,          // rotation unlocks at, 0 until scheduled
+    pub previous_authority: Pubkey, // authority this profile was rotated from, for migrations
+    pub bump: u8,                   // 1
+    pub recovery_authority: Pubkey, // key the guardians' approvals are for
+    pub pending_migrations: u32, // validators, stakes and proposals left under previous_authority
+    pub previous_profile: Pubkey, // profile PDA of previous_authority, still seeds its proposals
+}
 
-    #[account(mut)]
-    pub user_output_token_account: Account<'info, TokenAccount>,
+impl UserProfile {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_BIO_LEN: usize = 256;
+    pub const MAX_AVATAR_URI_LEN: usize = 200;
+    pub const MAX_WEBSITE_LEN: usize = 100;
 
-    #[account(mut)]
-    pub input_vault: Account<'info, TokenAccount>,
+    pub const MAX_GUARDIANS: usize = 5;
+    pub const ROTATION_TIMELOCK: i64 = 2 * 24 * 60 * 60; // 2 days
 
-    #[account(mut)]
-    pub output_vault: Account<'info, TokenAccount>,
+    // Everything except the bytes of the metadata strings; handle and guardian budgets are always reserved
+    pub const BASE_LEN: usize = 8 // discriminator
+        + 32
+        + 4
+        + 4
+        + 4
+        + 4
+        + (4 + HandleRecord::MAX_HANDLE_LEN)
+        + 4
+        + 4
+        + 4
+        + (4 + 32 * Self::MAX_GUARDIANS)
+        + 1
+        + 32
+        + 1
+        + 8
+        + 32
+        + 1
+        + 32
+        + 4
+        + 32;
+
+    /// Proposals are seeded from the profile that created them, which is
+    /// `previous_profile` for those opened before the last rotation
+    pub fn owns_proposal(&self, key: Pubkey, proposal: &Proposal) -> bool {
+        proposal.profile == key
+            || (self.previous_profile != Pubkey::default()
+                && proposal.profile == self.previous_profile)
+    }
 
-    #[account(
-        seeds = [b"vault", pool.key().as_ref(), output_token_mint.key().as_ref()],
-        bump
-    )]
-    /// CHECK: PDA signer for output_token_mint auth
-    pub vault_authority: UncheckedAccount<'info>,
+    /// Exact account size for the given metadata, used on init and realloc
+    pub fn space(name: &str, bio: &str, avatar_uri: &str, website: &str) -> usize {
+        Self::BASE_LEN + name.len() + bio.len() + avatar_uri.len() + website.len()
+    }
 
-    #[account(mut)]
-    pub output_token_mint: Account<'info, Mint>, // Used only as seed for PDA
+    pub fn validate_metadata(name: &str, bio: &str, avatar_uri: &str, website: &str) -> Result<()> {
+        require!(name.len() <= Self::MAX_NAME_LEN, CustomError::NameTooLong);
+        require!(bio.len() <= Self::MAX_BIO_LEN, CustomError::BioTooLong);
+        require!(
+            avatar_uri.len() <= Self::MAX_AVATAR_URI_LEN,
+            CustomError::AvatarUriTooLong
+        );
+        require!(
+            website.len() <= Self::MAX_WEBSITE_LEN,
+            CustomError::WebsiteTooLong
+        );
+        Ok(())
+    }
 
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
+    pub fn clear_pending_rotation(&mut self) {
+        self.pending_authority = Pubkey::default();
+        self.recovery_authority = Pubkey::default();
+        self.recovery_approvals = 0;
+        self.rotation_eta = 0;
+    }
+
+    /// Books the approval of guardian `index` for `new_authority` and returns the
+    /// tally. Approvals gather apart from the scheduled rotation and only replace
+    /// it once they reach the threshold, so one guardian can't override the owner
+    pub fn approve_recovery(&mut self, index: usize, new_authority: Pubkey, now: i64) -> u32 {
+        // Guardians backing a different key reset the tally
+        if self.recovery_authority != new_authority {
+            self.recovery_authority = new_authority;
+            self.recovery_approvals = 0;
+        }
+        self.recovery_approvals |= 1 << index;
 
-    pub token_program: Program<'info, Token>,
+        let approvals = self.recovery_approvals.count_ones();
+        if approvals >= self.guardian_threshold as u32 && self.pending_authority != new_authority {
+            self.pending_authority = new_authority;
+            self.rotation_eta = now + Self::ROTATION_TIMELOCK;
+        }
+        approvals
+    }
 }
 
-#[derive(Accounts)]
-pub struct AddLiquidity<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+#[account]
+pub struct HandleRecord {
+    pub handle: String,    // normalized handle, 4 + 32
+    pub profile: Pubkey,   // profile the handle resolves to
+    pub authority: Pubkey, // authority of that profile
+    pub claimed_at: i64,
+    pub bump: u8,
+}
 
-    #[account(mut)]
-    pub user_token_a: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_b: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_lp_token_account: Account<'info, TokenAccount>,
+impl HandleRecord {
+    pub const MIN_HANDLE_LEN: usize = 3;
+    pub const MAX_HANDLE_LEN: usize = 32;
 
-    #[account(mut)]
-    pub token_a_vault: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub token_b_vault: Account<'info, TokenAccount>,
+    pub const LEN: usize = 8 + (4 + Self::MAX_HANDLE_LEN) + 32 + 32 + 8 + 1;
+}
 
-    #[account(mut)]
-    pub lp_mint: Account<'info, Mint>,
+#[account]
+pub struct SessionKey {
+    pub profile: Pubkey,     // profile the session acts for
+    pub authority: Pubkey,   // owner of that profile
+    pub session_key: Pubkey, // ephemeral key allowed to sign
+    pub scopes: u8,          // bitmask of SCOPE_* instructions
+    pub expires_at: i64,
+    pub bump: u8,
+}
 
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
+impl SessionKey {
+    pub const SCOPE_VOTE: u8 = 1 << 0;
+    pub const SCOPE_CLAIM_REWARD: u8 = 1 << 1;
+    pub const SCOPE_STAKE: u8 = 1 << 2;
+    pub const ALL_SCOPES: u8 = Self::SCOPE_VOTE | Self::SCOPE_CLAIM_REWARD | Self::SCOPE_STAKE;
 
-    #[account(
-        seeds = [b"vault-authority", pool.key().as_ref()],
-        bump
-    )]
-    /// CHECK: PDA for mint authority
-    pub vault_authority: UncheckedAccount<'info>,
+    pub const MAX_DURATION: i64 = 7 * 24 * 60 * 60; // 7 days
+
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 1 + 8 + 1;
+
+    /// Passes if `signer` is the owner, or a live session of the owner scoped for `scope`
+    pub fn authorize(
+        session: Option<&Account<SessionKey>>,
+        authority: &Pubkey,
+        signer: &Pubkey,
+        scope: u8,
+    ) -> Result<()> {
+        if signer == authority {
+            return Ok(());
+        }
+
+        let session = session.ok_or(CustomError::Unauthorized)?;
+        require!(
+            session.authority == *authority && session.session_key == *signer,
+            CustomError::Unauthorized
+        );
+        require!(
+            session.scopes & scope != 0,
+            CustomError::SessionScopeNotAllowed
+        );
+        require!(
+            Clock::get()?.unix_timestamp < session.expires_at,
+            CustomError::SessionExpired
+        );
 
-    pub token_program: Program<'info, Token>,
+        Ok(())
+    }
 }
 
-#[derive(Accounts)]
-pub struct RemoveLiquidity<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+#[account]
+pub struct MintConfig {
+    pub admin: Pubkey,               // creator of the global mint
+    pub mint: Pubkey,                // global-mint PDA
+    pub max_supply: u64,             // hard cap on everything ever minted
+    pub validator_grant: u64,        // minted to each new validator
+    pub total_minted: u64,           // issued through this program so far, burns don't lower it
+    pub emission_start: i64,         // epoch 0 starts here
+    pub epoch_duration: i64,         // seconds per epoch
+    pub initial_epoch_emission: u64, // mint budget of epoch 0
+    pub halving_interval: u64,       // epochs between budget halvings, 0 = flat
+    pub current_epoch: u64,
+    pub minted_this_epoch: u64,
+    pub bump: u8,
+    pub pending_mint_authority: Pubkey, // proposed handover target
+    pub pending_freeze_authority: Pubkey,
+    pub handover_eta: i64, // 0 = no handover pending
+}
 
-    #[account(mut)]
-    pub user_lp_token: Account<'info, TokenAccount>,
+impl MintConfig {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 32 + 32 + 8;
 
-    #[account(mut)]
-    pub user_token_a: Account<'info, TokenAccount>,
+    pub const HANDOVER_TIMELOCK: i64 = 2 * 24 * 60 * 60; // 2 days
 
-    #[account(mut)]
-    pub user_token_b: Account<'info, TokenAccount>,
+    pub fn clear_pending_handover(&mut self) {
+        self.pending_mint_authority = Pubkey::default();
+        self.pending_freeze_authority = Pubkey::default();
+        self.handover_eta = 0;
+    }
 
-    #[account(mut)]
-    pub token_a_vault: Account<'info, TokenAccount>,
+    pub fn epoch_at(&self, now: i64) -> u64 {
+        (now.saturating_sub(self.emission_start) / self.epoch_duration) as u64
+    }
 
-    #[account(mut)]
-    pub token_b_vault: Account<'info, TokenAccount>,
+    pub fn epoch_emission(&self, epoch: u64) -> u64 {
+        if self.halving_interval == 0 {
+            return self.initial_epoch_emission;
+        }
+        let halvings = epoch / self.halving_interval;
+        self.initial_epoch_emission
+            .checked_shr(halvings.min(u32::MAX as u64) as u32)
+            .unwrap_or(0)
+    }
 
-    #[account(mut)]
-    pub lp_mint: Account<'info, Mint>,
+    /// Pools may reward in any mint the program can mint; only the global mint is
+    /// held to the policy
+    pub fn record_reward(&mut self, reward_mint: Pubkey, amount: u64, now: i64) -> Result<()> {
+        if reward_mint != self.mint {
+            return Ok(());
+        }
+        self.record_mint(amount, now)
+    }
 
-    #[account(mut)]
-    pub pool: Account<'info, Pool>,
+    /// Single policy check every mint path goes through: enforces the supply cap
+    /// and the current epoch's emission budget, then books the amount
+    pub fn record_mint(&mut self, amount: u64, now: i64) -> Result<()> {
+        let total = self
+            .total_minted
+            .checked_add(amount)
+            .ok_or(CustomError::SupplyCapExceeded)?;
+        require!(total <= self.max_supply, CustomError::SupplyCapExceeded);
+
+        let epoch = self.epoch_at(now);
+        if epoch != self.current_epoch {
+            self.current_epoch = epoch;
+            self.minted_this_epoch = 0;
+        }
 
-    #[account(
-        seeds = [b"vault-authority", pool.key().as_ref()],
-        bump
-    )]
-    /// CHECK: PDA used as vault authority
-    pub vault_authority: UncheckedAccount<'info>,
+        let minted = self
+            .minted_this_epoch
+            .checked_add(amount)
+            .ok_or(CustomError::EmissionLimitExceeded)?;
+        require!(
+            minted <= self.epoch_emission(epoch),
+            CustomError::EmissionLimitExceeded
+        );
 
-    pub token_program: Program<'info, Token>,
+        self.total_minted = total;
+        self.minted_this_epoch = minted;
+        Ok(())
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(owners: Vec<Pubkey>)]
-pub struct CreateMultisig<'info> {
-    #[account(
-        init,
-        seeds = [b"multisig", payer.key().as_ref()],
-        bump,
-        payer = payer,
-        space = Multisig::LEN
-    )]
-    pub multisig: Account<'info, Multisig>,
+#[account]
+pub struct MintMetadata {
+    pub mint: Pubkey,             // global-mint PDA
+    pub update_authority: Pubkey, // admin at first, can be handed to governance
+    pub name: String,             // 4 + 32
+    pub symbol: String,           // 4 + 10
+    pub uri: String,              // 4 + 200
+    pub bump: u8,
+}
 
-    #[account(mut)]
-    pub payer: Signer<'info>,
+impl MintMetadata {
+    pub const MAX_NAME_LEN: usize = 32;
+    pub const MAX_SYMBOL_LEN: usize = 10;
+    pub const MAX_URI_LEN: usize = 200;
 
-    pub system_program: Program<'info, System>,
-}
+    pub const LEN: usize = 8
+        + 32
+        + 32
+        + (4 + Self::MAX_NAME_LEN)
+        + (4 + Self::MAX_SYMBOL_LEN)
+        + (4 + Self::MAX_URI_LEN)
+        + 1;
 
-#[derive(Accounts)]
-#[instruction(tx_nonce: u8)]
-pub struct ProposeTransaction<'info> {
-    #[account(mut)]
-    pub proposer: Signer<'info>,
+    pub fn validate(name: &str, symbol: &str, uri: &str) -> Result<()> {
+        require!(
+            name.len() <= Self::MAX_NAME_LEN,
+            CustomError::TokenNameTooLong
+        );
+        require!(
+            symbol.len() <= Self::MAX_SYMBOL_LEN,
+            CustomError::SymbolTooLong
+        );
+        require!(uri.len() <= Self::MAX_URI_LEN, CustomError::UriTooLong);
+        Ok(())
+    }
+}
 
-    #[account(
-        mut,
-        seeds = [b"multisig", proposer.key().as_ref()],
-        bump
-    )]
-    pub multisig: Account<'info, Multisig>,
+#[account]
+pub struct Blocklist {
+    pub admin: Pubkey, // compliance admin, mint-config admin at init
+    pub bump: u8,
+}
 
-    #[account(
-        init,
-        payer = proposer,
-        space = Transaction::LEN,
-        seeds = [b"tx", multisig.key().as_ref(), &[tx_nonce]],
-        bump
-    )]
-    pub tx: Account<'info, Transaction>,
+impl Blocklist {
+    pub const LEN: usize = 8 + 32 + 1;
+}
 
-    pub system_program: Program<'info, System>,
+#[account]
+pub struct BlocklistEntry {
+    pub owner: Pubkey, // wallet owner barred from moving the global mint
+    pub blocked_at: i64,
+    pub bump: u8,
 }
 
-#[derive(Accounts)]
-pub struct ApproveTransaction<'info> {
-    #[account(mut)]
-    pub signer: Signer<'info>,
+impl BlocklistEntry {
+    pub const LEN: usize = 8 + 32 + 8 + 1;
 
-    #[account(
-        seeds = [b"multisig", signer.key().as_ref()],
-        bump
-    )]
-    pub multisig: Account<'info, Multisig>,
+    /// Whether the owner of `entry` may move `mint`. Entries only bar the global
+    /// mint, so other mints pass even for blocked owners
+    pub fn allows(mint: &Pubkey, entry: &AccountInfo) -> bool {
+        entry.data_is_empty() || *mint != global_mint_address()
+    }
+}
 
-    #[account(
-        mut,
-        seeds = [b"tx", multisig.key().as_ref()],
-        bump,
-        constraint = !tx.did_execute @ CustomError::AlreadyExecuted
-    )]
-    pub tx: Account<'info, Transaction>,
+#[account]
+pub struct Distributor {
+    pub id: u64,
+    pub admin: Pubkey,        // publishes the root, claws back after expiry
+    pub mint: Pubkey,         // global-mint PDA
+    pub vault: Pubkey,        // ATA of the distributor-authority PDA
+    pub root: [u8; 32],       // Merkle root over (index, claimant, amount) leaves
+    pub max_total_claim: u64, // amount the vault was funded with
+    pub total_claimed: u64,
+    pub max_num_nodes: u64, // leaves in the tree, sizes the bitmap
+    pub num_claimed: u64,
+    pub expires_at: i64, // claims close, clawback opens
+    pub clawed_back: bool,
+    pub authority_bump: u8,
+    pub bump: u8,
+    pub claimed_bitmap: Vec<u8>, // bit `index` set once claimed
 }
 
-#[derive(Accounts)]
-#[instruction(tx_nonce: u8)]
-pub struct ExecuteTransaction<'info> {
-    #[account(mut)]
-    pub executor: Signer<'info>,
+impl Distributor {
+    // Keeps the account under the 10KiB limit for accounts created via CPI
+    pub const MAX_NUM_NODES: u64 = 65_536;
 
-    #[account(
-        seeds = [b"multisig", executor.key().as_ref()],
-        bump
-    )]
-    pub multisig: Account<'info, Multisig>,
+    pub fn bitmap_len(max_num_nodes: u64) -> usize {
+        max_num_nodes.div_ceil(8) as usize
+    }
 
-    #[account(
-        mut,
-        seeds = [b"tx", multisig.key().as_ref(), &[tx_nonce]],
-        bump,
-        constraint = !tx.did_execute @ CustomError::AlreadyExecuted,
-        constraint = tx.multisig == multisig.key() @ CustomError::Unauthorized,
-    )]
-    pub tx: Account<'info, Transaction>,
+    pub fn space(max_num_nodes: u64) -> usize {
+        8 + 8
+            + 32
+            + 32
+            + 32
+            + 32
+            + 8
+            + 8
+            + 8
+            + 8
+            + 8
+            + 1
+            + 1
+            + 1
+            + (4 + Self::bitmap_len(max_num_nodes))
+    }
+
+    pub fn is_claimed(&self, index: u64) -> bool {
+        self.claimed_bitmap[(index / 8) as usize] & (1 << (index % 8)) != 0
+    }
+
+    pub fn set_claimed(&mut self, index: u64) {
+        self.claimed_bitmap[(index / 8) as usize] |= 1 << (index % 8);
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(tick_spacing: u16)]
-pub struct InitPoolClmm<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
+#[account]
+pub struct Allowance {
+    pub owner: Pubkey,
+    pub spender: Pubkey, // may call transfer_from
+    pub mint: Pubkey,
+    pub source: Pubkey, // owner's token account the delegation sits on
+    pub cap: u64,       // lifetime ceiling
+    pub spent: u64,
+    pub period_limit: u64, // ceiling per period, e.g. 1,000 tokens a day
+    pub period_duration: i64,
+    pub period_start: i64,
+    pub period_spent: u64,
+    pub delegate_bump: u8,
+    pub bump: u8,
+}
 
-    /// Pool state account (PDA)
-    #[account(
-        init,
-        payer = payer,
-        seeds = [b"pool-clmm", token_a_mint.key().as_ref(), token_b_mint.key().as_ref()],
-        bump,
-        space = PoolClmm::LEN
-    )]
-    pub pool_clmm: Account<'info, PoolClmm>,
+impl Allowance {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 1;
 
-    /// Token A and B mints
-    pub token_a_mint: Account<'info, Mint>,
-    pub token_b_mint: Account<'info, Mint>,
+    /// Rolls the period window forward if it has lapsed, then books `amount`
+    pub fn spend(&mut self, amount: u64, now: i64) -> Result<()> {
+        if now >= self.period_start + self.period_duration {
+            let elapsed = now - self.period_start;
+            self.period_start = now - elapsed % self.period_duration;
+            self.period_spent = 0;
+        }
 
-    /// Vaults to hold A/B liquidity
-    #[account(
-        init,
-        payer = payer,
-        token::mint = token_a_mint,
-        token::authority = pool_signer
-    )]
-    pub vault_a: Account<'info, TokenAccount>,
+        let spent = self
+            .spent
+            .checked_add(amount)
+            .ok_or(CustomError::AllowanceExceeded)?;
+        require!(spent <= self.cap, CustomError::AllowanceExceeded);
 
-    #[account(
-        init,
-        payer = payer,
-        token::mint = token_b_mint,
-        token::authority = pool_signer
-    )]
-    pub vault_b: Account<'info, TokenAccount>,
+        let period_spent = self
+            .period_spent
+            .checked_add(amount)
+            .ok_or(CustomError::PeriodLimitExceeded)?;
+        require!(
+            period_spent <= self.period_limit,
+            CustomError::PeriodLimitExceeded
+        );
 
-    /// PDA that signs for the vaults
-    #[account(
-        seeds = [b"signer", pool_clmm.key().as_ref()],
-        bump
-    )]
-    /// CHECK: Used only as a PDA signer
-    pub pool_signer: UncheckedAccount<'info>,
+        self.spent = spent;
+        self.period_spent = period_spent;
+        Ok(())
+    }
+}
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
-    pub rent: Sysvar<'info, Rent>,
+#[account]
+pub struct VestingAccount {
+    pub id: u64,
+    pub grantor: Pubkey,     // funded the grant, may revoke it
+    pub beneficiary: Pubkey, // receives unlocked tokens
+    pub mint: Pubkey,
+    pub vault: Pubkey, // ATA owned by this PDA
+    pub total: u64,    // cut down to the vested amount on revoke
+    pub claimed: u64,
+    pub start: i64,
+    pub cliff: i64,       // nothing unlocks before this
+    pub end: i64,         // everything is unlocked from here
+    pub step_period: i64, // unlock in whole steps (e.g. 30 days), 0 = continuous
+    pub revocable: bool,
+    pub revoked_at: i64, // 0 = active
+    pub bump: u8,
 }
 
-#[derive(Accounts)]
-#[instruction(tick_index: i32)]
-pub struct InitTick<'info> {
-    #[account(mut)]
-    pub payer: Signer<'info>,
+impl VestingAccount {
+    pub const LEN: usize = 8 + 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 8 + 8 + 1 + 8 + 1;
 
-    /// The CLMM pool this tick belongs to
-    pub pool_clmm: Account<'info, PoolClmm>,
+    pub fn vested_amount(&self, now: i64) -> u64 {
+        if self.revoked_at != 0 || now >= self.end {
+            return self.total;
+        }
+        if now < self.cliff {
+            return 0;
+        }
 
-    #[account(
-        init,
-        payer = payer,
-        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_index.to_le_bytes()],
-        bump,
-        space = Tick::LEN
-    )]
-    pub tick: Account<'info, Tick>,
+        let mut elapsed = now - self.start;
+        if self.step_period > 0 {
+            elapsed -= elapsed % self.step_period;
+        }
+        (self.total as u128 * elapsed as u128 / (self.end - self.start) as u128) as u64
+    }
+}
 
-    pub system_program: Program<'info, System>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ValidatorStatus {
+    Pending,  // awaiting admin approval
+    Active,   // may vote and take delegations
+    Jailed,   // slashed, until unjail after the cooldown
+    Exiting,  // unbonding before close, then the account is deleted
+    Inactive, // missed too many heartbeats, the next heartbeat reactivates
 }
 
-#[derive(Accounts)]
-pub struct OpenPosition<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
+#[account]
+pub struct ValidatorInfo {
+    pub id: u64,                     // 8
+    pub name: String,                // 4 + 32
+    pub status: ValidatorStatus,     // 1
+    pub authority: Pubkey,           // 32
+    pub profile: Pubkey,             // 32
+    pub migrated_at: i64,            // 8, last authority migration (0 if never)
+    pub bump: u8,                    //1
+    pub pool: Pubkey,                // 32, staking pool delegations accrue in (default = closed)
+    pub commission_bps: u16,         // 2, validator's cut of delegation rewards
+    pub delegated_stake: u64,        // 8, tokens held for delegators
+    pub total_shares: u64,           // 8, delegator claims on delegated_stake
+    pub acc_reward_per_share: u128,  // 16, delegator rewards per share, scaled
+    pub reward_per_token_paid: u128, // 16, pool accumulator at last accrual
+    pub pending_commission: u64,     // 8
+    pub jailed_until: i64,           // 8, set by slashing, 0 = not jailed
+    pub unbonding_until: i64,        // 8, close_validator allowed from here once Exiting
+    pub grant_amount: u64,           // 8, minted at init, burned on close
+    pub voting_weight: u64,          // 8, counted in registry.active_weight while Active
+}
 
-    #[account(mut)]
-    pub pool_clmm: Account<'info, PoolClmm>,
+impl ValidatorInfo {
+    pub const LEN: usize =
+        8 + 8 + 4 + 32 + 1 + 32 + 32 + 8 + 1 + 32 + 2 + 8 + 8 + 16 + 16 + 8 + 8 + 8 + 8 + 8;
 
-    #[account(mut)]
-    pub user_token_a: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_token_b: Account<'info, TokenAccount>,
+    pub const MAX_COMMISSION_BPS: u16 = 10_000;
 
-    #[account(mut)]
-    pub vault_a: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub vault_b: Account<'info, TokenAccount>,
+    /// Stake behind a jailed or exiting validator stays slashable until its jail
+    /// time and unbonding period have run out
+    pub fn require_unbonded(&self, now: i64) -> Result<()> {
+        match self.status {
+            ValidatorStatus::Jailed => {
+                require!(now >= self.jailed_until, CustomError::ValidatorJailed)
+            }
+            ValidatorStatus::Exiting => require!(
+                now >= self.unbonding_until && now >= self.jailed_until,
+                CustomError::ValidatorUnbonding
+            ),
+            _ => {}
+        }
+        Ok(())
+    }
 
-    #[account(
-        seeds = [b"signer", pool_clmm.key().as_ref()],
-        bump = pool_clmm.signer_bump
-    )]
-    /// CHECK: Only used for CPI signing
-    pub pool_signer: UncheckedAccount<'info>,
+    /// Books this validator's slice of pool emissions since the last accrual, scaled by
+    /// its uptime score: the commission goes to the validator, the rest to its
+    /// delegators per share. The slice missed through downtime is never earned
+    pub fn accrue_rewards(&mut self, pool: &StakingPool, score_bps: u64) {
+        let emitted = self.delegated_stake as u128
+            * (pool.acc_reward_per_token - self.reward_per_token_paid)
+            / StakingPool::REWARD_PRECISION;
+        let earned = (emitted * score_bps as u128 / 10_000) as u64;
+        self.reward_per_token_paid = pool.acc_reward_per_token;
+        if earned == 0 {
+            return;
+        }
 
-    #[account(mut)]
-    tick_lower: Account<'info, Tick>,
-    #[account(mut)]
-    tick_upper: Account<'info, Tick>,
+        let commission = (earned as u128 * self.commission_bps as u128 / 10_000) as u64;
+        self.pending_commission = self.pending_commission.saturating_add(commission);
+        self.acc_reward_per_share += (earned - commission) as u128 * StakingPool::REWARD_PRECISION
+            / self.total_shares as u128;
+    }
 
-    #[account(
-        init,
-        payer = owner,
-        seeds = [
-            b"position",
-            owner.key().as_ref(),
-            pool_clmm.key().as_ref(),
-            &tick_lower.tick_index.to_le_bytes(),
-            &tick_upper.tick_index.to_le_bytes(),
-        ],
-        bump,
-        space = Position::LEN
-    )]
-    pub user_position: Account<'info, Position>,
+    pub fn shares_for(&self, amount: u64) -> u64 {
+        if self.total_shares == 0 || self.delegated_stake == 0 {
+            return amount;
+        }
+        (amount as u128 * self.total_shares as u128 / self.delegated_stake as u128) as u64
+    }
 
-    pub token_program: Program<'info, Token>,
-    pub system_program: Program<'info, System>,
+    pub fn stake_for(&self, shares: u64) -> u64 {
+        if self.total_shares == 0 {
+            return 0;
+        }
+        (shares as u128 * self.delegated_stake as u128 / self.total_shares as u128) as u64
+    }
 }
 
-#[derive(Accounts)]
-pub struct DecreaseLiquidity<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
+#[account]
+pub struct Proposal {
+    pub id: u64,             // unique per profile
+    pub profile: Pubkey,     // creator profile pda
+    pub title: String,       // 4 + N
+    pub description: String, // 4 + M
+    pub created_at: i64,     // UNIX timestamp
+    pub deadline: i64,       // voting end
+    pub yes_votes: u64,      // number of yes voters
+    pub no_votes: u64,       // number of no voters
+    pub yes_weight: u64,     // stake behind yes
+    pub no_weight: u64,      // stake behind no
+    pub abstain_votes: u64,
+    pub abstain_weight: u64, // counts toward quorum only
+    pub veto_votes: u64,
+    pub veto_weight: u64, // counts as no, and vetoes past VETO_THRESHOLD_BPS
+    pub bump: u8,
+    pub executed: bool,
+    pub quorum_weight: u64, // weight that must vote, resolved from the realm at creation
+    pub approval_threshold_bps: u16, // yes share of the cast weight must exceed this
+    pub status: ProposalStatus,
+    pub vote_records: u64, // open VoteRecord accounts, all closed before the proposal can be
+    pub instructions: Vec<ProposalInstruction>, // run by execute_proposal once passed
+}
 
-    /// The pool for which the position was opened
-    #[account(
-        mut,
-        has_one = vault_a,
-        has_one = vault_b
-    )]
-    pub pool_clmm: Account<'info, PoolClmm>,
+impl Proposal {
+    // created_at and deadline, then the four vote/weight tally pairs
+    pub const LEN: usize =
+        8 + 8 + 32 + (4 + 64) + (4 + 256) + 8 * 2 + 8 * 8 + 1 + 1 + 8 + 2 + 1 + 8 + 4;
 
-    /// Position owned by the user
-    #[account(
-        mut,
-        seeds = [
-            b"position",
-            owner.key().as_ref(),
-            pool_clmm.key().as_ref(),
-            &tick_lower.tick_index.to_le_bytes(),
-            &tick_upper.tick_index.to_le_bytes(),
-        ],
-        bump,
-        has_one = owner,
-        has_one = pool_clmm
-    )]
-    pub user_position: Account<'info, Position>,
+    pub fn space(instructions: &[ProposalInstruction]) -> usize {
+        Self::LEN
+            + instructions
+                .iter()
+                .map(ProposalInstruction::space)
+                .sum::<usize>()
+    }
+
+    pub const VETO_THRESHOLD_BPS: u128 = 3_334; // a third of the cast weight
+
+    fn counters(&mut self, choice: VoteChoice) -> (&mut u64, &mut u64) {
+        match choice {
+            VoteChoice::Yes => (&mut self.yes_votes, &mut self.yes_weight),
+            VoteChoice::No => (&mut self.no_votes, &mut self.no_weight),
+            VoteChoice::Abstain => (&mut self.abstain_votes, &mut self.abstain_weight),
+            VoteChoice::NoWithVeto => (&mut self.veto_votes, &mut self.veto_weight),
+        }
+    }
+
+    pub fn tally(&mut self, choice: VoteChoice, weight: u64) -> Result<()> {
+        let (votes, total) = self.counters(choice);
+        *votes = votes.checked_add(1).ok_or(CustomError::MathOverflow)?;
+        *total = total.checked_add(weight).ok_or(CustomError::MathOverflow)?;
+        Ok(())
+    }
+
+    pub fn untally(&mut self, choice: VoteChoice, weight: u64) {
+        let (votes, total) = self.counters(choice);
+        *votes = votes.saturating_sub(1);
+        *total = total.saturating_sub(weight);
+    }
+
+    pub fn cast_weight(&self) -> u64 {
+        self.yes_weight
+            .saturating_add(self.no_weight)
+            .saturating_add(self.abstain_weight)
+            .saturating_add(self.veto_weight)
+    }
+
+    pub fn reached_quorum(&self) -> bool {
+        self.cast_weight() >= self.quorum_weight
+    }
 
-    /// Lower tick account
-    #[account(
-        mut,
-        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_lower.tick_index.to_le_bytes()],
-        bump
-    )]
-    pub tick_lower: Account<'info, Tick>,
+    pub fn vetoed(&self) -> bool {
+        self.veto_weight as u128 * 10_000 > Self::VETO_THRESHOLD_BPS * self.cast_weight() as u128
+    }
 
-    /// Upper tick account
-    #[account(
-        mut,
-        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_upper.tick_index.to_le_bytes()],
-        bump
-    )]
-    pub tick_upper: Account<'info, Tick>,
+    /// Yes must exceed the threshold share of the decisive (non-abstain) weight
+    pub fn approved(&self) -> bool {
+        let decisive = self.yes_weight as u128 + self.no_weight as u128 + self.veto_weight as u128;
+        !self.vetoed()
+            && self.yes_weight as u128 * 10_000 > self.approval_threshold_bps as u128 * decisive
+    }
 
-    /// Vault A (Token A liquidity vault)
-    #[account(mut)]
-    pub vault_a: Account<'info, TokenAccount>,
+    pub fn outcome(&self) -> ProposalStatus {
+        if !self.reached_quorum() {
+            ProposalStatus::QuorumFailed
+        } else if self.approved() {
+            ProposalStatus::Passed
+        } else {
+            ProposalStatus::Rejected
+        }
+    }
+}
 
-    /// Vault B (Token B liquidity vault)
-    #[account(mut)]
-    pub vault_b: Account<'info, TokenAccount>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ProposalStatus {
+    Voting,
+    Passed,
+    Rejected,
+    QuorumFailed,
+    Cancelled,
+}
 
-    /// Vault signer PDA (pool auth)
-    #[account(
-        seeds = [b"signer", pool_clmm.key().as_ref()],
-        bump
-    )]
-    /// CHECK: PDA auth only
-    pub pool_signer: UncheckedAccount<'info>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum VoteChoice {
+    Yes,
+    No,
+    Abstain,
+    NoWithVeto,
+}
 
-    /// Users receiving token accounts
-    #[account(mut)]
-    pub user_token_a: Account<'info, TokenAccount>,
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Quorum {
+    Absolute { weight: u64 },
+    PercentOfTotal { bps: u16 }, // of the registry's active weight
+}
 
-    #[account(mut)]
-    pub user_token_b: Account<'info, TokenAccount>,
+impl Quorum {
+    pub fn weight(&self, active_weight: u64) -> u64 {
+        match *self {
+            Quorum::Absolute { weight } => weight,
+            Quorum::PercentOfTotal { bps } => {
+                (active_weight as u128 * bps as u128).div_ceil(10_000) as u64
+            }
+        }
+    }
+}
 
-    pub token_program: Program<'info, Token>,
+/// Governance rules for new proposals. Changed only by the governance treasury
+#[account]
+pub struct Realm {
+    pub min_voting_period: i64, // seconds between creation and deadline
+    pub max_voting_period: i64,
+    pub quorum: Quorum,
+    pub approval_threshold_bps: u16,
+    pub min_proposal_weight: u64, // stake needed to propose, 0 = any profile
+    pub bump: u8,
 }
 
-#[derive(Accounts)]
-pub struct CollectFees<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
+impl Realm {
+    pub const LEN: usize = 8 + 8 + 8 + (1 + 8) + 2 + 8 + 1;
 
-    /// Pool for which fees are collected
-    #[account(
-        mut,
-        has_one = vault_a,
-        has_one = vault_b
-    )]
-    pub pool_clmm: Account<'info, PoolClmm>,
+    pub fn validate(
+        min_voting_period: i64,
+        max_voting_period: i64,
+        quorum: Quorum,
+        approval_threshold_bps: u16,
+    ) -> Result<()> {
+        require!(
+            min_voting_period > 0 && min_voting_period <= max_voting_period,
+            CustomError::InvalidRealmConfig
+        );
+        require!(
+            approval_threshold_bps < 10_000,
+            CustomError::InvalidRealmConfig
+        );
+        if let Quorum::PercentOfTotal { bps } = quorum {
+            require!(bps <= 10_000, CustomError::InvalidRealmConfig);
+        }
+        Ok(())
+    }
+}
 
-    /// Users position account
-    #[account(
-        mut,
-        seeds = [
-            b"position",
-            owner.key().as_ref(),
-            pool_clmm.key().as_ref(),
-            &tick_lower.tick_index.to_le_bytes(),
-            &tick_upper.tick_index.to_le_bytes(),
-        ],
-        bump,
-        has_one = owner,
-        has_one = pool_clmm
-    )]
-    pub user_position: Account<'info, Position>,
+/// One instruction of an executable proposal, using the multisig's account encoding
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ProposalInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<TransactionAccount>,
+    pub data: Vec<u8>,
+}
 
-    /// Lower and upper tick accounts (used for future fee tracking logic)
-    #[account(
-        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_lower.tick_index.to_le_bytes()],
-        bump
-    )]
-    pub tick_lower: Account<'info, Tick>,
+impl ProposalInstruction {
+    pub const MAX_INSTRUCTIONS: usize = 4;
 
-    #[account(
-        seeds = [b"tick", pool_clmm.key().as_ref(), &tick_upper.tick_index.to_le_bytes()],
-        bump
-    )]
-    pub tick_upper: Account<'info, Tick>,
+    pub fn space(&self) -> usize {
+        32 + 4 + TransactionAccount::LEN * self.accounts.len() + 4 + self.data.len()
+    }
 
-    /// Vault that hold fees
-    #[account(mut)]
-    pub vault_a: Account<'info, TokenAccount>,
+    pub fn validate(instructions: &[ProposalInstruction]) -> Result<()> {
+        require!(
+            instructions.len() <= Self::MAX_INSTRUCTIONS,
+            CustomError::TooManyInstructions
+        );
+        for ix in instructions {
+            require!(
+                ix.accounts.len() <= Transaction::MAX_ACCOUNTS
+                    && ix.data.len() <= Transaction::MAX_DATA,
+                CustomError::InstructionTooLarge
+            );
+        }
+        Ok(())
+    }
+}
 
-    #[account(mut)]
-    pub vault_b: Account<'info, TokenAccount>,
+#[account]
+pub struct VoteRecord {
+    pub proposal: Pubkey,
+    pub validator: Pubkey,
+    pub stake_vault: Pubkey,
+    pub choice: VoteChoice,
+    pub weight: u64, // stake counted for this vote
+    pub timestamp: i64,
+    pub bump: u8,
+    pub payer: Pubkey, // owner or session key that paid the rent, refunded on close
+}
 
-    /// PDA signer that controls the vaults
-    #[account(
-        seeds = [b"signer", pool_clmm.key().as_ref()],
-        bump
-    )]
-    /// CHECK: PDA signer only
-    pub pool_signer: UncheckedAccount<'info>,
+impl VoteRecord {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 1 + 8 + 8 + 1 + 32;
+}
 
-    /// User receiving accounts
-    #[account(mut)]
-    pub user_token_a: Account<'info, TokenAccount>,
+#[account]
+pub struct StakeVault {
+    pub owner: Pubkey,   // 32 bytes
+    pub profile: Pubkey, // 32 bytes
+    pub vault: Pubkey,
+    pub pool: Pubkey,
+    pub amount: u64,           // 8 bytes
+    pub reward_collected: u64, // 8 bytes
+    pub start_stake_time: i64, // 8 bytes
+    pub weight_since: i64,     // 8 bytes, when the current voting weight was established
+    pub bump: u8,              // 1 byte
+}
 
-    #[account(mut)]
-    pub user_token_b: Account<'info, TokenAccount>,
+impl StakeVault {
+    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 8 + 1;
 
-    pub token_program: Program<'info, Token>,
+    /// Tokens in an operator's stake vault, zero if it never staked
+    pub fn amount_in(info: &AccountInfo) -> Result<u64> {
+        if info.data_is_empty() {
+            return Ok(0);
+        }
+        require_keys_eq!(
+            *info.owner,
+            crate::ID,
+            ErrorCode::AccountOwnedByWrongProgram
+        );
+        Ok(StakeVault::try_deserialize(&mut &info.try_borrow_data()?[..])?.amount)
+    }
 }
 
-#[derive(Accounts)]
-pub struct SwapClmm<'info> {
-    #[account(mut)]
-    pub user: Signer<'info>,
+#[account]
+pub struct StakingPool {
+    pub id: u64,
+    pub name: String,
+    pub authority: Pubkey, // pool creator
+    pub stake_mint: Pubkey,
+    pub reward_mint: Pubkey,
+    pub reward_per_second: u64,
+    pub total_staked: u64,
+    pub lock_period: u64,
+    pub reward_vault: Pubkey,
+    pub reward_vault_authority_bump: u8,
+    pub reward_balance: u64, // total tokens available for rewards
+    pub paused: bool,
+    pub bump: u8,
+    pub delegated_stake: u64, // held by validators for their delegators
+    pub acc_reward_per_token: u128, // delegation rewards per delegated token, scaled
+    pub last_reward_time: i64,
+}
 
-    #[account(mut)]
-    pub pool_clmm: Account<'info, PoolClmm>,
+impl StakingPool {
+    pub const LEN: usize =
+        8 + 8 + (4 + 32) + 32 + 32 + 32 + 8 + 8 + 8 + 32 + 1 + 8 + 1 + 1 + 8 + 16 + 8;
 
-    #[account(mut)]
-    pub user_source_token: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub user_destination_token: Account<'info, TokenAccount>,
+    pub const REWARD_PRECISION: u128 = 1_000_000_000_000;
 
-    #[account(mut)]
-    pub vault_source: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub vault_destination: Account<'info, TokenAccount>,
+    /// Delegated stake shares `reward_per_second` across all validators of the pool
+    pub fn accrue_delegation_rewards(&mut self, now: i64) {
+        if now <= self.last_reward_time {
+            return;
+        }
+        if self.delegated_stake > 0 && !self.paused {
+            let emitted = (now - self.last_reward_time) as u128 * self.reward_per_second as u128;
+            self.acc_reward_per_token +=
+                emitted * Self::REWARD_PRECISION / self.delegated_stake as u128;
+        }
+        self.last_reward_time = now;
+    }
 
-    #[account(
-        seeds = [b"signer", pool_clmm.key().as_ref()],
-        bump
-    )]
-    /// CHECK: PDA auth only
-    pub pool_signer: UncheckedAccount<'info>,
+    /// Rewards are minted, so the refilled balance is the budget every claim draws down
+    pub fn spend_rewards(&mut self, amount: u64) -> Result<()> {
+        self.reward_balance = self
+            .reward_balance
+            .checked_sub(amount)
+            .ok_or(CustomError::InsufficientRewardVault)?;
+        Ok(())
+    }
 
-    pub token_program: Program<'info, Token>,
+    /// Settles delegation rewards up to `now` at the old rate before applying the change
+    pub fn update_config(
+        &mut self,
+        reward_per_second: u64,
+        lock_period: u64,
+        paused: bool,
+        now: i64,
+    ) {
+        self.accrue_delegation_rewards(now);
+        self.reward_per_second = reward_per_second;
+        self.lock_period = lock_period;
+        self.paused = paused;
+    }
 }
 
-#[derive(Accounts)]
-#[instruction(tick_lower: i32, tick_upper: i32)]
-pub struct ClosePosition<'info> {
-    #[account(mut)]
-    pub owner: Signer<'info>,
+#[account]
+pub struct ValidatorRegistry {
+    pub next_id: u64,         // id handed to the next init_validator
+    pub validator_count: u64, // registered and not yet closed
+    pub active_count: u64,
+    pub total_weight: u64,  // all staked tokens, voting or not
+    pub active_weight: u64, // what Active validators can vote with: the PercentOfTotal denominator
+    pub bump: u8,
+}
 
-    /// Pool the position belongs to
-    #[account(mut)]
-    pub pool_clmm: Account<'info, PoolClmm>,
+impl ValidatorRegistry {
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + 8 + 1;
 
-    /// Users position to close
-    #[account(
-        mut,
-        close = owner,
-        seeds = [
-            b"position",
-            owner.key().as_ref(),
-            pool_clmm.key().as_ref(),
-            &tick_lower.to_le_bytes(),
-            &tick_upper.to_le_bytes()
-        ],
-        bump,
-        has_one = owner,
-        has_one = pool_clmm
-    )]
-    pub user_position: Account<'info, Position>,
+    /// Replaces what `validator` counts towards `active_weight`; anything but an
+    /// Active validator counts nothing
+    pub fn set_voting_weight(&mut self, validator: &mut ValidatorInfo, weight: u64) {
+        let weight = if validator.status == ValidatorStatus::Active {
+            weight
+        } else {
+            0
+        };
+        self.active_weight = self
+            .active_weight
+            .saturating_sub(validator.voting_weight)
+            .saturating_add(weight);
+        validator.voting_weight = weight;
+    }
 }
 
-#[derive(Accounts)]
-pub struct CollectProtocolFees<'info> {
-    #[account(mut)]
-    pub authority: Signer<'info>,
-
-    #[account(
-        mut,
-        has_one = vault_a,
-        has_one = vault_b,
-        has_one = authority
-    )]
-    pub pool_clmm: Account<'info, PoolClmm>,
+/// Fixed-size page of validator addresses indexed by id, so clients can list
+/// validators page by page. Closed validators leave a default key behind
+#[account]
+pub struct RegistryPage {
+    pub page: u64,
+    pub validators: Vec<Pubkey>, // slot = id % SIZE
+    pub bump: u8,
+}
 
-    #[account(mut)]
-    pub vault_a: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub vault_b: Account<'info, TokenAccount>,
+impl RegistryPage {
+    pub const SIZE: u64 = 64;
+    pub const LEN: usize = 8 + 8 + (4 + 32 * Self::SIZE as usize) + 1;
 
-    #[account(
-        seeds = [b"signer", pool_clmm.key().as_ref()],
-        bump = pool_clmm.signer_bump
-    )]
-    /// CHECK: PDA signer for vault auth
-    pub pool_signer: UncheckedAccount<'info>,
+    pub fn page_of(id: u64) -> u64 {
+        id / Self::SIZE
+    }
 
-    #[account(mut)]
-    pub protocol_fee_recipient_a: Account<'info, TokenAccount>,
-    #[account(mut)]
-    pub protocol_fee_recipient_b: Account<'info, TokenAccount>,
+    pub fn replace(&mut self, id: u64, key: Pubkey) {
+        self.validators[(id % Self::SIZE) as usize] = key;
+    }
 
-    pub token_program: Program<'info, Token>,
+    pub fn remove(&mut self, id: u64) {
+        self.replace(id, Pubkey::default());
+    }
 }
 
-// ----------------- ACCOUNT STRUCTS ---------------------
-
 #[account]
-pub struct UserProfile {
-    pub authority: Pubkey, // 32
-    pub name: String,      // 4 (length prefix) + max characters
-    pub bump: u8,          // 1
+pub struct ValidatorConfig {
+    pub admin: Pubkey, // may slash; mint-config admin at init, can be handed to governance
+    pub jail_duration: i64, // seconds a slashed validator stays jailed
+    pub slash_destination: Pubkey, // token account receiving slashed stake, default = burn
+    pub bump: u8,
+    pub unbonding_period: i64, // seconds between begin_exit and close_validator
+    pub epoch_length: i64,     // seconds per heartbeat epoch, copied into new uptime accounts
+    pub max_missed_epochs: u64, // consecutive misses before the crank deactivates a validator
 }
 
-impl UserProfile {
-    pub const LEN: usize = 8 + 32 + 4 + 32 + 1; // 8 = discriminator
+impl ValidatorConfig {
+    pub const LEN: usize = 8 + 32 + 8 + 32 + 1 + 8 + 8 + 8;
 }
 
+/// Heartbeat history of a validator over the last `WINDOW` epochs, a ring buffer
+/// indexed by epoch
 #[account]
-pub struct ValidatorInfo {
-    pub id: u64,           // 8
-    pub name: String,      // 4 + 32
-    pub is_active: bool,   // 1
-    pub authority: Pubkey, // 32
-    pub profile: Pubkey,   // 32
-    pub bump: u8,          //1
+pub struct ValidatorUptime {
+    pub validator_id: u64,
+    pub epoch_length: i64, // fixed at creation so config changes don't reindex the history
+    pub start_epoch: u64,  // epoch the validator registered in
+    pub last_epoch: u64,   // latest heartbeat, start_epoch before the first one
+    pub heartbeats: [bool; 32], // slot = epoch % WINDOW
+    pub bump: u8,
 }
 
-impl ValidatorInfo {
-    pub const LEN: usize = 8 + 8 + 4 + 32 + 1 + 32 + 32 + 1;
-}
+impl ValidatorUptime {
+    pub const WINDOW: u64 = 32;
+    pub const LEN: usize = 8 + 8 + 8 + 8 + 8 + Self::WINDOW as usize + 1;
+
+    /// Voting weight of `stake` behind this validator: absent operators count for less
+    pub fn weigh(&self, stake: u64, now: i64) -> u64 {
+        (stake as u128 * self.score_bps(now) as u128 / 10_000) as u64
+    }
+
+    pub fn epoch_at(&self, now: i64) -> u64 {
+        (now / self.epoch_length) as u64
+    }
+
+    fn slot(epoch: u64) -> usize {
+        (epoch % Self::WINDOW) as usize
+    }
+
+    pub fn record(&mut self, epoch: u64) -> Result<()> {
+        if epoch == self.last_epoch {
+            require!(
+                !self.heartbeats[Self::slot(epoch)],
+                CustomError::HeartbeatAlreadyRecorded
+            );
+        } else {
+            // Slots of the epochs skipped since the last heartbeat still hold
+            // entries from a window ago
+            let from = (self.last_epoch + 1).max(epoch.saturating_sub(Self::WINDOW - 1));
+            for missed in from..epoch {
+                self.heartbeats[Self::slot(missed)] = false;
+            }
+        }
+        self.heartbeats[Self::slot(epoch)] = true;
+        self.last_epoch = epoch;
+        Ok(())
+    }
+
+    /// Epochs in a row that ended without a heartbeat; the current one doesn't count
+    pub fn missed_epochs(&self, epoch: u64) -> u64 {
+        let first_unseen = if self.heartbeats[Self::slot(self.last_epoch)] {
+            self.last_epoch + 1
+        } else {
+            self.last_epoch
+        };
+        epoch.saturating_sub(first_unseen)
+    }
 
-#[account]
-pub struct Proposal {
-    pub id: u64,             // unique per profile
-    pub profile: Pubkey,     // creator profile pda
-    pub title: String,       // 4 + N
-    pub description: String, // 4 + M
-    pub created_at: i64,     // UNIX timestamp
-    pub deadline: i64,       // voting end
-    pub yes_votes: u64,
-    pub no_votes: u64,
-    pub bump: u8,
-}
+    /// Share of epochs in the window with a heartbeat, in bps. The current epoch
+    /// only counts once it has one, and a validator with no finished epoch yet
+    /// scores full
+    pub fn score_bps(&self, now: i64) -> u64 {
+        let epoch = self.epoch_at(now);
+        let end = if self.last_epoch == epoch && self.heartbeats[Self::slot(epoch)] {
+            epoch
+        } else {
+            match epoch.checked_sub(1) {
+                Some(end) => end,
+                None => return 10_000,
+            }
+        };
+        if end < self.start_epoch {
+            return 10_000;
+        }
 
-impl Proposal {
-    pub const LEN: usize = 8 + 8 + 32 + (4 + 64) + (4 + 256) + 8 + 8 + 8 + 1;
+        let from = self.start_epoch.max((end + 1).saturating_sub(Self::WINDOW));
+        let up = (from..=end)
+            .filter(|e| *e <= self.last_epoch && self.heartbeats[Self::slot(*e)])
+            .count() as u64;
+        up * 10_000 / (end - from + 1)
+    }
 }
 
 #[account]
-pub struct VoteRecord {
-    pub proposal: Pubkey,
+pub struct SlashEvent {
     pub validator: Pubkey,
-    pub vote: bool,
+    pub evidence_hash: [u8; 32], // hash of the off-chain evidence, one slash per hash
+    pub bps: u16,
+    pub self_slashed: u64,      // taken from the operator's stake vault
+    pub delegated_slashed: u64, // taken from stake delegated to the validator
+    pub destination: Pubkey,    // default = burned
+    pub slashed_by: Pubkey,
     pub timestamp: i64,
     pub bump: u8,
 }
 
-impl VoteRecord {
-    pub const LEN: usize = 8 + 32 + 32 + 1 + 8 + 1;
+impl SlashEvent {
+    pub const LEN: usize = 8 + 32 + 32 + 2 + 8 + 8 + 32 + 32 + 8 + 1;
 }
 
 #[account]
-pub struct StakeVault {
-    pub owner: Pubkey,   // 32 bytes
-    pub profile: Pubkey, // 32 bytes
-    pub vault: Pubkey,
-    pub pool: Pubkey,
-    pub amount: u64,           // 8 bytes
-    pub reward_collected: u64, // 8 bytes
-    pub start_stake_time: i64, // 8 bytes
-    pub bump: u8,              // 1 byte
+pub struct Delegation {
+    pub delegator: Pubkey,
+    pub validator: Pubkey,
+    pub shares: u64,         // claim on validator.delegated_stake
+    pub reward_debt: u128,   // shares * acc_reward_per_share already accounted for
+    pub pending_reward: u64, // settled but not yet claimed
+    pub bump: u8,
 }
 
-impl StakeVault {
-    pub const LEN: usize = 8 + 32 + 32 + 32 + 32 + 8 + 8 + 8 + 1;
-}
+impl Delegation {
+    pub const LEN: usize = 8 + 32 + 32 + 8 + 16 + 8 + 1;
 
-#[account]
-pub struct StakingPool {
-    pub id: u64,
-    pub name: String,
-    pub authority: Pubkey, // pool creator
-    pub stake_mint: Pubkey,
-    pub reward_mint: Pubkey,
-    pub reward_per_second: u64,
-    pub total_staked: u64,
-    pub lock_period: u64,
-    pub reward_vault: Pubkey,
-    pub reward_vault_authority_bump: u8,
-    pub reward_balance: u64, // total tokens available for rewards
-    pub paused: bool,
-    pub bump: u8,
-}
+    /// Moves rewards earned on the current shares into `pending_reward`
+    pub fn settle(&mut self, validator: &ValidatorInfo) {
+        let accumulated =
+            self.shares as u128 * validator.acc_reward_per_share / StakingPool::REWARD_PRECISION;
+        let earned = accumulated.saturating_sub(self.reward_debt) as u64;
+        self.pending_reward = self.pending_reward.saturating_add(earned);
+        self.reward_debt = accumulated;
+    }
 
-impl StakingPool {
-    pub const LEN: usize = 8 + 8 + (4 + 32) + 32 + 32 + 32 + 8 + 8 + 8 + 32 + 1 + 1 + 1;
+    pub fn set_shares(&mut self, shares: u64, validator: &ValidatorInfo) {
+        self.settle(validator);
+        self.shares = shares;
+        self.reward_debt =
+            shares as u128 * validator.acc_reward_per_share / StakingPool::REWARD_PRECISION;
+    }
 }
 
 #[account]
@@ -2618,6 +8177,40 @@ impl Position {
         16; // fee_growth_checkpoint_b
 }
 
+// ----------------- EVENTS ---------------------
+
+#[event]
+pub struct MintAuthorityProposed {
+    pub mint: Pubkey,
+    pub new_mint_authority: Pubkey,
+    pub new_freeze_authority: Pubkey,
+    pub eta: i64,
+}
+
+#[event]
+pub struct MintAuthorityCancelled {
+    pub mint: Pubkey,
+    pub new_mint_authority: Pubkey,
+}
+
+#[event]
+pub struct MintAuthorityAccepted {
+    pub mint: Pubkey,
+    pub new_mint_authority: Pubkey,
+    pub new_freeze_authority: Pubkey,
+}
+
+#[event]
+pub struct BatchTransferred {
+    pub mint: Pubkey,
+    pub sender: Pubkey,
+    pub index: u16,
+    pub recipient: Pubkey,
+    pub ata: Pubkey,
+    pub amount: u64,
+    pub created_ata: bool,
+}
+
 // ----------------- ERROR ---------------------
 
 #[error_code]
@@ -2690,6 +8283,231 @@ pub enum CustomError {
 
     #[msg("No protocol fees available for collection")]
     NoProtocolFeesToCollect,
+
+    #[msg("Profile name exceeds 32 bytes")]
+    NameTooLong,
+
+    #[msg("Profile bio exceeds 256 bytes")]
+    BioTooLong,
+
+    #[msg("Avatar URI exceeds 200 bytes")]
+    AvatarUriTooLong,
+
+    #[msg("Website exceeds 100 bytes")]
+    WebsiteTooLong,
+
+    #[msg("Profile is still referenced by validators, proposals, stakes or a handle")]
+    ProfileInUse,
+
+    #[msg("Handles must be 3-32 characters of a-z, 0-9 or underscore")]
+    InvalidHandle,
+
+    #[msg("Profile already has a handle")]
+    HandleAlreadySet,
+
+    #[msg("Guardians must be unique, exclude the authority and fit the threshold (max 5)")]
+    InvalidGuardianSet,
+
+    #[msg("Signer is not a guardian of this profile")]
+    NotAGuardian,
+
+    #[msg("New authority must differ from the current one")]
+    InvalidNewAuthority,
+
+    #[msg("No authority rotation is pending for this key")]
+    NoRotationPending,
+
+    #[msg("Authority rotation is still timelocked")]
+    RotationTimelocked,
+
+    #[msg("Handle record must be supplied to migrate a profile with a handle")]
+    HandleRecordRequired,
+
+    #[msg("Validator was migrated after this proposal was created")]
+    ValidatorMigrated,
+
+    #[msg("Session scopes must be a non-empty subset of vote, claim_reward and stake")]
+    InvalidSessionScopes,
+
+    #[msg("Session key is not allowed to sign this instruction")]
+    SessionScopeNotAllowed,
+
+    #[msg("Session key has expired")]
+    SessionExpired,
+
+    #[msg("Invalid mint configuration")]
+    InvalidMintConfig,
+
+    #[msg("Minting would exceed the global max supply")]
+    SupplyCapExceeded,
+
+    #[msg("Minting would exceed this epoch's emission budget")]
+    EmissionLimitExceeded,
+
+    #[msg("Symbol too long")]
+    SymbolTooLong,
+
+    #[msg("URI too long")]
+    UriTooLong,
+
+    #[msg("Mint authority is no longer the program PDA")]
+    MintAuthorityMismatch,
+
+    #[msg("No mint authority handover is pending")]
+    NoHandoverPending,
+
+    #[msg("Mint authority handover is still timelocked")]
+    HandoverTimelocked,
+
+    #[msg("Owner is blocklisted")]
+    Blocklisted,
+
+    #[msg("Batch must pair each amount with a [wallet, ata, blocklist entry] account triple")]
+    InvalidBatch,
+
+    #[msg("Recipient token account does not match the mint or wallet")]
+    InvalidRecipientAccount,
+
+    #[msg("Invalid distributor parameters")]
+    InvalidDistributor,
+
+    #[msg("Invalid Merkle proof")]
+    InvalidProof,
+
+    #[msg("This allocation was already claimed")]
+    AlreadyClaimed,
+
+    #[msg("Distributor has expired")]
+    DistributorExpired,
+
+    #[msg("Distributor has not expired yet")]
+    DistributorNotExpired,
+
+    #[msg("Claim exceeds the distributor's funded total")]
+    ClaimExceedsMax,
+
+    #[msg("Vesting schedule must satisfy start <= cliff <= end with a valid step")]
+    InvalidVestingSchedule,
+
+    #[msg("Nothing has vested since the last claim")]
+    NothingToClaim,
+
+    #[msg("This vesting schedule cannot be revoked")]
+    VestingNotRevocable,
+
+    #[msg("Vesting schedule was already revoked")]
+    VestingRevoked,
+
+    #[msg("Allowance needs a cap, a period limit within it and a period length")]
+    InvalidAllowance,
+
+    #[msg("Transfer exceeds the remaining allowance")]
+    AllowanceExceeded,
+
+    #[msg("Transfer exceeds the allowance for this period")]
+    PeriodLimitExceeded,
+
+    #[msg("Stake was not in place when the proposal was created")]
+    StakeTooRecent,
+
+    #[msg("No stake to vote with")]
+    NoVotingPower,
+
+    #[msg("Commission cannot exceed 10000 bps")]
+    InvalidCommission,
+
+    #[msg("Validator is not accepting delegations from this pool")]
+    ValidatorPoolMismatch,
+
+    #[msg("Validator is inactive")]
+    ValidatorInactive,
+
+    #[msg("Not enough delegated shares")]
+    InsufficientShares,
+
+    #[msg("Source and destination validator must differ")]
+    InvalidRedelegation,
+
+    #[msg("Validator still holds delegated stake")]
+    ValidatorHasDelegations,
+
+    #[msg("Jail and unbonding durations cannot be negative")]
+    InvalidValidatorConfig,
+
+    #[msg("Slash must be between 1 and 9999 bps")]
+    InvalidSlashBps,
+
+    #[msg("Accounts holding the slashed stake were not provided")]
+    SlashAccountsMissing,
+
+    #[msg("Validator is jailed")]
+    ValidatorJailed,
+
+    #[msg("Not allowed in the validator's current status")]
+    InvalidValidatorStatus,
+
+    #[msg("Validator is still unbonding")]
+    ValidatorUnbonding,
+
+    #[msg("Authority no longer holds the validator grant")]
+    GrantNotHeld,
+
+    #[msg("Heartbeat already recorded for this epoch")]
+    HeartbeatAlreadyRecorded,
+
+    #[msg("Validator has not missed enough heartbeats")]
+    ValidatorStillLive,
+
+    #[msg("Voting on this proposal has not ended")]
+    VotingNotEnded,
+
+    #[msg("Proposal did not pass")]
+    ProposalNotPassed,
+
+    #[msg("Proposal has no instructions to execute")]
+    NothingToExecute,
+
+    #[msg("Too many instructions in the proposal")]
+    TooManyInstructions,
+
+    #[msg("Proposal instruction has too many accounts or too much data")]
+    InstructionTooLarge,
+
+    #[msg("Invalid realm configuration")]
+    InvalidRealmConfig,
+
+    #[msg("Voting period is outside the realm's limits")]
+    InvalidVotingPeriod,
+
+    #[msg("Not enough stake to create a proposal")]
+    InsufficientProposalWeight,
+
+    #[msg("Proposal is no longer open for voting")]
+    ProposalNotVoting,
+
+    #[msg("Proposal has not been finalized")]
+    ProposalNotFinalized,
+
+    #[msg("Proposal already has votes")]
+    ProposalHasVotes,
+
+    #[msg("Passed proposal has not been executed")]
+    ProposalNotExecuted,
+
+    #[msg("Validators, stakes or proposals of the previous authority still need migrating")]
+    MigrationsPending,
+
+    #[msg("Token name exceeds 32 bytes")]
+    TokenNameTooLong,
+
+    #[msg("Proposal did not reach quorum")]
+    QuorumNotReached,
+
+    #[msg("Arithmetic overflow")]
+    MathOverflow,
+
+    #[msg("Operator stake stays bonded until its validators are closed")]
+    OperatorStakeBonded,
 }
 
 // Utitility fns
@@ -2697,13 +8515,830 @@ fn integer_sqrt(value: u64) -> u64 {
     (value as f64).sqrt() as u64
 }
 
-fn update_fee_growth_global(pool: &mut Account<PoolClmm>, fee_amount_a: u64, fee_amount_b: u64) {
-    if pool.liquidity > 0 {
-        let fee_growth_a = (fee_amount_a as u128) << 64 / pool.liquidity;
-        let fee_growth_b = (fee_amount_b as u128) << 64 / pool.liquidity;
+/// Canonical form of a handle: leading '@' dropped, ASCII lowercased, [a-z0-9_] only
+pub fn normalize_handle(handle: &str) -> Result<String> {
+    let handle = handle
+        .strip_prefix('@')
+        .unwrap_or(handle)
+        .to_ascii_lowercase();
+
+    require!(
+        (HandleRecord::MIN_HANDLE_LEN..=HandleRecord::MAX_HANDLE_LEN).contains(&handle.len()),
+        CustomError::InvalidHandle
+    );
+    require!(
+        handle
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_'),
+        CustomError::InvalidHandle
+    );
+
+    Ok(handle)
+}
+
+/// PDA seed for a handle record: sha256 of the handle with '@' and case stripped
+pub fn handle_seed(handle: &str) -> [u8; 32] {
+    let handle = handle
+        .strip_prefix('@')
+        .unwrap_or(handle)
+        .to_ascii_lowercase();
+    anchor_lang::solana_program::hash::hash(handle.as_bytes()).to_bytes()
+}
+
+/// Address of the program's own mint, the only one the blocklist governs
+pub fn global_mint_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"global-mint"], &crate::ID).0
+}
+
+/// `transfer_checked` that forwards the context's remaining accounts to the token program,
+/// so Token-2022 mints with a transfer hook can resolve their extra accounts
+pub fn transfer_checked_with_hook<'info>(
+    ctx: CpiContext<'_, '_, '_, 'info, TransferChecked<'info>>,
+    amount: u64,
+    decimals: u8,
+) -> Result<()> {
+    let mut ix = spl_token_2022::instruction::transfer_checked(
+        ctx.program.key,
+        ctx.accounts.from.key,
+        ctx.accounts.mint.key,
+        ctx.accounts.to.key,
+        ctx.accounts.authority.key,
+        &[],
+        amount,
+        decimals,
+    )?;
+
+    let mut account_infos = vec![
+        ctx.accounts.from,
+        ctx.accounts.mint,
+        ctx.accounts.to,
+        ctx.accounts.authority,
+    ];
+    for account in ctx.remaining_accounts {
+        ix.accounts.push(if account.is_writable {
+            AccountMeta::new(*account.key, account.is_signer)
+        } else {
+            AccountMeta::new_readonly(*account.key, account.is_signer)
+        });
+        account_infos.push(account);
+    }
+
+    anchor_lang::solana_program::program::invoke_signed(&ix, &account_infos, ctx.signer_seeds)
+        .map_err(Into::into)
+}
+
+/// Burns slashed stake, or moves it to `destination` when the validator config names one
+#[allow(clippy::too_many_arguments)]
+pub fn slash_tokens<'info>(
+    token_program: &Interface<'info, TokenInterface>,
+    mint: &InterfaceAccount<'info, Mint>,
+    from: AccountInfo<'info>,
+    authority: AccountInfo<'info>,
+    destination: Option<&InterfaceAccount<'info, TokenAccount>>,
+    remaining_accounts: &[AccountInfo<'info>],
+    signer_seeds: &[&[&[u8]]],
+    amount: u64,
+) -> Result<()> {
+    match destination {
+        Some(destination) => transfer_checked_with_hook(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                TransferChecked {
+                    from,
+                    mint: mint.to_account_info(),
+                    to: destination.to_account_info(),
+                    authority,
+                },
+                signer_seeds,
+            )
+            .with_remaining_accounts(remaining_accounts.to_vec()),
+            amount,
+            mint.decimals,
+        ),
+        None => token_interface::burn(
+            CpiContext::new_with_signer(
+                token_program.to_account_info(),
+                Burn {
+                    mint: mint.to_account_info(),
+                    from,
+                    authority,
+                },
+                signer_seeds,
+            ),
+            amount,
+        ),
+    }
+}
+
+/// Merkle leaf for a distributor allocation. Leaves and inner nodes are
+/// domain-separated so an inner node can never be passed off as a leaf
+pub fn merkle_leaf(index: u64, claimant: &Pubkey, amount: u64) -> [u8; 32] {
+    anchor_lang::solana_program::hash::hashv(&[
+        &[0u8],
+        &index.to_le_bytes(),
+        claimant.as_ref(),
+        &amount.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Parent of two nodes; pairs are sorted so proofs need no left/right flags
+pub fn merkle_parent(a: &[u8; 32], b: &[u8; 32]) -> [u8; 32] {
+    let (lo, hi) = if a <= b { (a, b) } else { (b, a) };
+    anchor_lang::solana_program::hash::hashv(&[&[1u8], lo, hi]).to_bytes()
+}
+
+pub fn merkle_verify(proof: &[[u8; 32]], root: &[u8; 32], leaf: [u8; 32]) -> bool {
+    proof
+        .iter()
+        .fold(leaf, |node, sibling| merkle_parent(&node, sibling))
+        == *root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Builds every level of a tree bottom-up; an odd node out is promoted as-is
+    fn build_tree(leaves: &[[u8; 32]]) -> Vec<Vec<[u8; 32]>> {
+        let mut levels = vec![leaves.to_vec()];
+        while levels.last().unwrap().len() > 1 {
+            let next = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => merkle_parent(a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+        levels
+    }
+
+    fn proof_for(levels: &[Vec<[u8; 32]>], mut index: usize) -> Vec<[u8; 32]> {
+        let mut proof = Vec::new();
+        for level in &levels[..levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        proof
+    }
+
+    fn allocations(n: u64) -> Vec<(u64, Pubkey, u64)> {
+        (0..n)
+            .map(|i| (i, Pubkey::new_unique(), 1_000 * (i + 1)))
+            .collect()
+    }
+
+    fn distributor(max_num_nodes: u64) -> Distributor {
+        Distributor {
+            id: 0,
+            admin: Pubkey::default(),
+            mint: Pubkey::default(),
+            vault: Pubkey::default(),
+            root: [0; 32],
+            max_total_claim: 0,
+            total_claimed: 0,
+            max_num_nodes,
+            num_claimed: 0,
+            expires_at: 0,
+            clawed_back: false,
+            authority_bump: 0,
+            bump: 0,
+            claimed_bitmap: vec![0; Distributor::bitmap_len(max_num_nodes)],
+        }
+    }
+
+    #[test]
+    fn every_leaf_verifies_for_any_tree_size() {
+        for n in 1..=17 {
+            let allocs = allocations(n);
+            let leaves: Vec<_> = allocs
+                .iter()
+                .map(|(i, who, amount)| merkle_leaf(*i, who, *amount))
+                .collect();
+            let levels = build_tree(&leaves);
+            let root = levels.last().unwrap()[0];
+
+            for (i, leaf) in leaves.iter().enumerate() {
+                let proof = proof_for(&levels, i);
+                assert!(merkle_verify(&proof, &root, *leaf), "n={n} i={i}");
+            }
+        }
+    }
+
+    #[test]
+    fn tampered_claims_are_rejected() {
+        let allocs = allocations(8);
+        let leaves: Vec<_> = allocs
+            .iter()
+            .map(|(i, who, amount)| merkle_leaf(*i, who, *amount))
+            .collect();
+        let levels = build_tree(&leaves);
+        let root = levels.last().unwrap()[0];
+
+        let (index, claimant, amount) = allocs[3];
+        let proof = proof_for(&levels, 3);
+        assert!(merkle_verify(
+            &proof,
+            &root,
+            merkle_leaf(index, &claimant, amount)
+        ));
+
+        assert!(!merkle_verify(
+            &proof,
+            &root,
+            merkle_leaf(index, &claimant, amount + 1)
+        ));
+        assert!(!merkle_verify(
+            &proof,
+            &root,
+            merkle_leaf(index + 1, &claimant, amount)
+        ));
+        assert!(!merkle_verify(
+            &proof,
+            &root,
+            merkle_leaf(index, &Pubkey::new_unique(), amount)
+        ));
+        // Another leaf's proof doesn't carry over
+        assert!(!merkle_verify(
+            &proof_for(&levels, 4),
+            &root,
+            merkle_leaf(index, &claimant, amount)
+        ));
+    }
+
+    #[test]
+    fn truncated_or_padded_proofs_are_rejected() {
+        let allocs = allocations(8);
+        let leaves: Vec<_> = allocs
+            .iter()
+            .map(|(i, who, amount)| merkle_leaf(*i, who, *amount))
+            .collect();
+        let levels = build_tree(&leaves);
+        let root = levels.last().unwrap()[0];
+
+        let mut proof = proof_for(&levels, 5);
+        assert!(!merkle_verify(&proof[..proof.len() - 1], &root, leaves[5]));
+
+        proof.push(leaves[0]);
+        assert!(!merkle_verify(&proof, &root, leaves[5]));
+    }
+
+    #[test]
+    fn claimed_bitmap_tracks_each_index() {
+        let mut d = distributor(20);
+        assert_eq!(d.claimed_bitmap.len(), 3);
+
+        for i in [0, 7, 8, 19] {
+            assert!(!d.is_claimed(i));
+            d.set_claimed(i);
+            assert!(d.is_claimed(i));
+        }
+        assert!(!d.is_claimed(1));
+        assert!(!d.is_claimed(18));
+    }
+
+    fn vesting(step_period: i64) -> VestingAccount {
+        VestingAccount {
+            id: 0,
+            grantor: Pubkey::default(),
+            beneficiary: Pubkey::default(),
+            mint: Pubkey::default(),
+            vault: Pubkey::default(),
+            total: 1_200,
+            claimed: 0,
+            start: 0,
+            cliff: 300,
+            end: 1_200,
+            step_period,
+            revocable: true,
+            revoked_at: 0,
+            bump: 0,
+        }
+    }
+
+    #[test]
+    fn linear_vesting_respects_the_cliff() {
+        let v = vesting(0);
+        assert_eq!(v.vested_amount(-10), 0);
+        assert_eq!(v.vested_amount(299), 0);
+        assert_eq!(v.vested_amount(300), 300);
+        assert_eq!(v.vested_amount(601), 601);
+        assert_eq!(v.vested_amount(1_200), 1_200);
+        assert_eq!(v.vested_amount(5_000), 1_200);
+    }
+
+    #[test]
+    fn stepped_vesting_unlocks_in_whole_periods() {
+        let v = vesting(400);
+        assert_eq!(v.vested_amount(300), 0);
+        assert_eq!(v.vested_amount(400), 400);
+        assert_eq!(v.vested_amount(799), 400);
+        assert_eq!(v.vested_amount(800), 800);
+        assert_eq!(v.vested_amount(1_199), 800);
+        assert_eq!(v.vested_amount(1_200), 1_200);
+    }
+
+    #[test]
+    fn allowance_enforces_period_and_lifetime_caps() {
+        let mut a = Allowance {
+            owner: Pubkey::default(),
+            spender: Pubkey::default(),
+            mint: Pubkey::default(),
+            source: Pubkey::default(),
+            cap: 2_500,
+            spent: 0,
+            period_limit: 1_000,
+            period_duration: 86_400,
+            period_start: 0,
+            period_spent: 0,
+            delegate_bump: 0,
+            bump: 0,
+        };
+
+        a.spend(600, 10).unwrap();
+        a.spend(400, 20).unwrap();
+        assert!(a.spend(1, 86_399).is_err());
+
+        // Two days later the window snaps to the start of the current period
+        a.spend(1_000, 2 * 86_400 + 5).unwrap();
+        assert_eq!(a.period_start, 2 * 86_400);
+
+        // 2,000 of 2,500 used; a fresh period can't exceed the lifetime cap
+        assert!(a.spend(600, 3 * 86_400).is_err());
+        a.spend(500, 3 * 86_400).unwrap();
+        assert_eq!(a.spent, 2_500);
+    }
+
+    #[test]
+    fn delegation_rewards_split_commission_then_shares() {
+        let mut pool = StakingPool {
+            id: 0,
+            name: String::new(),
+            authority: Pubkey::default(),
+            stake_mint: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            reward_per_second: 100,
+            total_staked: 0,
+            lock_period: 0,
+            reward_vault: Pubkey::default(),
+            reward_vault_authority_bump: 0,
+            reward_balance: 0,
+            paused: false,
+            bump: 0,
+            delegated_stake: 0,
+            acc_reward_per_token: 0,
+            last_reward_time: 0,
+        };
+        let mut validator = ValidatorInfo {
+            id: 0,
+            name: String::new(),
+            status: ValidatorStatus::Active,
+            authority: Pubkey::default(),
+            profile: Pubkey::default(),
+            migrated_at: 0,
+            bump: 0,
+            pool: Pubkey::default(),
+            commission_bps: 1_000,
+            delegated_stake: 0,
+            total_shares: 0,
+            acc_reward_per_share: 0,
+            reward_per_token_paid: 0,
+            pending_commission: 0,
+            jailed_until: 0,
+            unbonding_until: 0,
+            grant_amount: 0,
+            voting_weight: 0,
+        };
+        let delegation = || Delegation {
+            delegator: Pubkey::default(),
+            validator: Pubkey::default(),
+            shares: 0,
+            reward_debt: 0,
+            pending_reward: 0,
+            bump: 0,
+        };
+
+        // Alice delegates 250 at t=0, Bob 250 at t=10
+        let mut alice = delegation();
+        let shares = validator.shares_for(250);
+        alice.set_shares(shares, &validator);
+        validator.delegated_stake += 250;
+        validator.total_shares += shares;
+        pool.delegated_stake += 250;
+
+        pool.accrue_delegation_rewards(10);
+        validator.accrue_rewards(&pool, 10_000);
+        let mut bob = delegation();
+        let shares = validator.shares_for(250);
+        bob.set_shares(shares, &validator);
+        validator.delegated_stake += 250;
+        validator.total_shares += shares;
+        pool.delegated_stake += 250;
+
+        pool.accrue_delegation_rewards(20);
+        validator.accrue_rewards(&pool, 10_000);
+        alice.settle(&validator);
+        bob.settle(&validator);
+
+        // 2,000 emitted: 10% commission, then 900 + half of 900 to Alice
+        assert_eq!(validator.pending_commission, 200);
+        assert_eq!(alice.pending_reward, 1_350);
+        assert_eq!(bob.pending_reward, 450);
+
+        // At half uptime only half of the next 1,000 is earned at all
+        pool.accrue_delegation_rewards(30);
+        validator.accrue_rewards(&pool, 5_000);
+        alice.settle(&validator);
+        bob.settle(&validator);
+        assert_eq!(validator.pending_commission, 250);
+        assert_eq!(alice.pending_reward, 1_575);
+        assert_eq!(bob.pending_reward, 675);
+
+        // Stake lost by the validator lowers what every share redeems for
+        validator.delegated_stake = 400;
+        assert_eq!(validator.stake_for(alice.shares), 200);
+        assert_eq!(validator.shares_for(80), 100);
+    }
+
+    #[test]
+    fn delegations_stay_bonded_while_jailed_or_exiting() {
+        let mut validator = ValidatorInfo {
+            id: 0,
+            name: String::new(),
+            status: ValidatorStatus::Active,
+            authority: Pubkey::default(),
+            profile: Pubkey::default(),
+            migrated_at: 0,
+            bump: 0,
+            pool: Pubkey::default(),
+            commission_bps: 0,
+            delegated_stake: 0,
+            total_shares: 0,
+            acc_reward_per_share: 0,
+            reward_per_token_paid: 0,
+            pending_commission: 0,
+            jailed_until: 0,
+            unbonding_until: 0,
+            grant_amount: 0,
+            voting_weight: 0,
+        };
+        assert!(validator.require_unbonded(0).is_ok());
+
+        validator.status = ValidatorStatus::Jailed;
+        validator.jailed_until = 20;
+        assert!(validator.require_unbonded(19).is_err());
+        assert!(validator.require_unbonded(20).is_ok());
+
+        // Exiting waits for both the unbonding period and any jail time
+        validator.status = ValidatorStatus::Exiting;
+        validator.unbonding_until = 10;
+        assert!(validator.require_unbonded(15).is_err());
+        validator.unbonding_until = 30;
+        assert!(validator.require_unbonded(25).is_err());
+        assert!(validator.require_unbonded(30).is_ok());
+    }
+
+    #[test]
+    fn pool_config_changes_apply_from_the_update_on() {
+        let mut pool = StakingPool {
+            id: 0,
+            name: String::new(),
+            authority: Pubkey::default(),
+            stake_mint: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            reward_per_second: 100,
+            total_staked: 0,
+            lock_period: 0,
+            reward_vault: Pubkey::default(),
+            reward_vault_authority_bump: 0,
+            reward_balance: 0,
+            paused: false,
+            bump: 0,
+            delegated_stake: 1_000,
+            acc_reward_per_token: 0,
+            last_reward_time: 0,
+        };
+        let per_token = |tokens: u128| tokens * StakingPool::REWARD_PRECISION / 1_000;
+
+        // 10s at 100/s, then 10s at 300/s
+        pool.update_config(300, 0, false, 10);
+        assert_eq!(pool.acc_reward_per_token, per_token(1_000));
+        pool.accrue_delegation_rewards(20);
+        assert_eq!(pool.acc_reward_per_token, per_token(4_000));
+
+        // Time before a pause still pays out, time while paused does not
+        pool.update_config(300, 0, true, 30);
+        assert_eq!(pool.acc_reward_per_token, per_token(7_000));
+        pool.update_config(300, 0, false, 40);
+        assert_eq!(pool.acc_reward_per_token, per_token(7_000));
+    }
+
+    #[test]
+    fn claims_spend_the_refilled_budget() {
+        let mut pool = StakingPool {
+            id: 0,
+            name: String::new(),
+            authority: Pubkey::default(),
+            stake_mint: Pubkey::default(),
+            reward_mint: Pubkey::default(),
+            reward_per_second: 0,
+            total_staked: 0,
+            lock_period: 0,
+            reward_vault: Pubkey::default(),
+            reward_vault_authority_bump: 0,
+            reward_balance: 100,
+            paused: false,
+            bump: 0,
+            delegated_stake: 0,
+            acc_reward_per_token: 0,
+            last_reward_time: 0,
+        };
+
+        pool.spend_rewards(60).unwrap();
+        assert!(pool.spend_rewards(41).is_err());
+        pool.spend_rewards(40).unwrap();
+        assert_eq!(pool.reward_balance, 0);
+        assert!(pool.spend_rewards(1).is_err());
+    }
+
+    #[test]
+    fn uptime_ring_buffer_scores_recent_epochs() {
+        let mut uptime = ValidatorUptime {
+            validator_id: 0,
+            epoch_length: 10,
+            start_epoch: 100,
+            last_epoch: 100,
+            heartbeats: [false; 32],
+            bump: 0,
+        };
+        let at = |epoch: u64| epoch as i64 * 10;
+
+        // Nothing finished yet
+        assert_eq!(uptime.score_bps(at(100)), 10_000);
+        assert_eq!(uptime.missed_epochs(102), 2);
+
+        uptime.record(100).unwrap();
+        assert!(uptime.record(100).is_err());
+        assert_eq!(uptime.score_bps(at(100)), 10_000);
+
+        // 101 and 102 passed without a heartbeat, 103 is still running
+        assert_eq!(uptime.score_bps(at(103)), 3_333);
+        assert_eq!(uptime.missed_epochs(103), 2);
+
+        uptime.record(103).unwrap();
+        assert_eq!(uptime.score_bps(at(103)), 5_000);
+        assert_eq!(uptime.missed_epochs(104), 0);
+        assert_eq!(uptime.missed_epochs(105), 1);
+
+        // A full window later only the newest heartbeat is left in the buffer
+        uptime.record(135).unwrap();
+        assert_eq!(uptime.score_bps(at(135)), 312);
+    }
+
+    #[test]
+    fn proposals_need_quorum_and_threshold() {
+        let quorum = Quorum::PercentOfTotal { bps: 2_000 };
+        assert_eq!(quorum.weight(1_001), 201);
+
+        let mut proposal = Proposal {
+            id: 0,
+            profile: Pubkey::default(),
+            title: String::new(),
+            description: String::new(),
+            created_at: 0,
+            deadline: 0,
+            yes_votes: 0,
+            no_votes: 0,
+            yes_weight: 120,
+            no_weight: 80,
+            abstain_votes: 0,
+            abstain_weight: 0,
+            veto_votes: 0,
+            veto_weight: 0,
+            bump: 0,
+            executed: false,
+            quorum_weight: quorum.weight(1_001),
+            approval_threshold_bps: 6_000,
+            status: ProposalStatus::Voting,
+            vote_records: 0,
+            instructions: vec![],
+        };
+        // 200 of 201 weight voted, and exactly 60% yes doesn't exceed the threshold
+        assert!(!proposal.reached_quorum());
+        assert!(!proposal.approved());
+        assert_eq!(proposal.outcome(), ProposalStatus::QuorumFailed);
+
+        proposal.no_weight = 81;
+        proposal.yes_weight = 122;
+        assert!(proposal.reached_quorum());
+        assert!(proposal.approved());
+        assert_eq!(proposal.outcome(), ProposalStatus::Passed);
+
+        // Abstaining helps reach quorum without diluting the yes share
+        proposal.tally(VoteChoice::Abstain, 500).unwrap();
+        assert!(proposal.approved());
+
+        // Veto weight counts as no, and past a third of the cast weight it vetoes
+        proposal.tally(VoteChoice::NoWithVeto, 300).unwrap();
+        assert!(!proposal.vetoed());
+        assert!(!proposal.approved());
+        assert_eq!(proposal.outcome(), ProposalStatus::Rejected);
+        proposal.untally(VoteChoice::Abstain, 500);
+        assert!(proposal.vetoed());
+    }
+
+    #[test]
+    fn active_weight_counts_only_what_active_validators_can_vote() {
+        let mut registry = ValidatorRegistry {
+            next_id: 0,
+            validator_count: 0,
+            active_count: 0,
+            total_weight: 0,
+            active_weight: 0,
+            bump: 0,
+        };
+        let validator = |status| ValidatorInfo {
+            id: 0,
+            name: String::new(),
+            status,
+            authority: Pubkey::default(),
+            profile: Pubkey::default(),
+            migrated_at: 0,
+            bump: 0,
+            pool: Pubkey::default(),
+            commission_bps: 0,
+            delegated_stake: 0,
+            total_shares: 0,
+            acc_reward_per_share: 0,
+            reward_per_token_paid: 0,
+            pending_commission: 0,
+            jailed_until: 0,
+            unbonding_until: 0,
+            grant_amount: 0,
+            voting_weight: 0,
+        };
+        let mut alice = validator(ValidatorStatus::Active);
+        let mut bob = validator(ValidatorStatus::Active);
+        let mut carol = validator(ValidatorStatus::Pending);
+
+        registry.set_voting_weight(&mut alice, 600);
+        registry.set_voting_weight(&mut bob, 400);
+        registry.set_voting_weight(&mut carol, 500);
+        assert_eq!(registry.active_weight, 1_000);
+
+        // Refreshing replaces the old count; leaving Active drops it
+        registry.set_voting_weight(&mut alice, 480);
+        bob.status = ValidatorStatus::Inactive;
+        registry.set_voting_weight(&mut bob, 0);
+        assert_eq!(registry.active_weight, 480);
+        assert_eq!(bob.voting_weight, 0);
+
+        // A full quorum is reachable by the active set at its current uptime
+        let mut proposal = Proposal {
+            id: 0,
+            profile: Pubkey::default(),
+            title: String::new(),
+            description: String::new(),
+            created_at: 0,
+            deadline: 0,
+            yes_votes: 0,
+            no_votes: 0,
+            yes_weight: 0,
+            no_weight: 0,
+            abstain_votes: 0,
+            abstain_weight: 0,
+            veto_votes: 0,
+            veto_weight: 0,
+            bump: 0,
+            executed: false,
+            quorum_weight: Quorum::PercentOfTotal { bps: 10_000 }.weight(registry.active_weight),
+            approval_threshold_bps: 5_000,
+            status: ProposalStatus::Voting,
+            vote_records: 0,
+            instructions: vec![],
+        };
+        proposal
+            .tally(VoteChoice::Yes, alice.voting_weight)
+            .unwrap();
+        assert_eq!(proposal.outcome(), ProposalStatus::Passed);
+    }
 
-        pool.fee_growth_global_a = pool.fee_growth_global_a.checked_add(fee_growth_a).unwrap();
+    #[test]
+    fn guardians_override_an_owner_rotation_only_at_threshold() {
+        let guardians: Vec<Pubkey> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let mut profile = UserProfile {
+            authority: Pubkey::new_unique(),
+            name: String::new(),
+            bio: String::new(),
+            avatar_uri: String::new(),
+            website: String::new(),
+            handle: String::new(),
+            validator_count: 0,
+            proposal_count: 0,
+            active_stakes: 0,
+            guardians,
+            guardian_threshold: 2,
+            pending_authority: Pubkey::default(),
+            recovery_approvals: 0,
+            rotation_eta: 0,
+            previous_authority: Pubkey::default(),
+            bump: 0,
+            recovery_authority: Pubkey::default(),
+            pending_migrations: 0,
+            previous_profile: Pubkey::default(),
+        };
+        let owner_pick = Pubkey::new_unique();
+        let recovery_key = Pubkey::new_unique();
+
+        // As scheduled by propose_rotation
+        profile.pending_authority = owner_pick;
+        profile.rotation_eta = 100;
+
+        assert_eq!(profile.approve_recovery(0, recovery_key, 50), 1);
+        assert_eq!(profile.pending_authority, owner_pick);
+        assert_eq!(profile.rotation_eta, 100);
+
+        // Switching keys starts the tally over
+        assert_eq!(profile.approve_recovery(1, Pubkey::new_unique(), 50), 1);
+        assert_eq!(profile.approve_recovery(0, recovery_key, 50), 1);
+        assert_eq!(profile.approve_recovery(0, recovery_key, 50), 1);
+        assert_eq!(profile.pending_authority, owner_pick);
+
+        assert_eq!(profile.approve_recovery(2, recovery_key, 60), 2);
+        assert_eq!(profile.pending_authority, recovery_key);
+        assert_eq!(profile.rotation_eta, 60 + UserProfile::ROTATION_TIMELOCK);
+
+        // Late approvals don't push the timelock back
+        assert_eq!(profile.approve_recovery(1, recovery_key, 70), 3);
+        assert_eq!(profile.rotation_eta, 60 + UserProfile::ROTATION_TIMELOCK);
+    }
+
+    #[test]
+    fn rotated_profiles_still_own_their_old_proposals() {
+        let (old_key, new_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut profile = UserProfile {
+            authority: Pubkey::new_unique(),
+            name: String::new(),
+            bio: String::new(),
+            avatar_uri: String::new(),
+            website: String::new(),
+            handle: String::new(),
+            validator_count: 0,
+            proposal_count: 1,
+            active_stakes: 0,
+            guardians: vec![],
+            guardian_threshold: 0,
+            pending_authority: Pubkey::default(),
+            recovery_approvals: 0,
+            rotation_eta: 0,
+            previous_authority: Pubkey::default(),
+            bump: 0,
+            recovery_authority: Pubkey::default(),
+            pending_migrations: 0,
+            previous_profile: Pubkey::default(),
+        };
+        let proposal = |profile: Pubkey| Proposal {
+            id: 0,
+            profile,
+            title: String::new(),
+            description: String::new(),
+            created_at: 0,
+            deadline: 0,
+            yes_votes: 0,
+            no_votes: 0,
+            yes_weight: 0,
+            no_weight: 0,
+            abstain_votes: 0,
+            abstain_weight: 0,
+            veto_votes: 0,
+            veto_weight: 0,
+            bump: 0,
+            executed: false,
+            quorum_weight: 0,
+            approval_threshold_bps: 0,
+            status: ProposalStatus::Voting,
+            vote_records: 0,
+            instructions: vec![],
+        };
+
+        // Never rotated: a default proposal profile must not match the unset field
+        assert!(profile.owns_proposal(new_key, &proposal(new_key)));
+        assert!(!profile.owns_proposal(new_key, &proposal(Pubkey::default())));
+
+        profile.previous_profile = old_key;
+        assert!(profile.owns_proposal(new_key, &proposal(old_key)));
+        assert!(!profile.owns_proposal(new_key, &proposal(Pubkey::new_unique())));
+    }
 
-        pool.fee_growth_global_b = pool.fee_growth_global_b.checked_add(fee_growth_b).unwrap();
+    #[test]
+    fn largest_distributor_fits_a_cpi_created_account() {
+        assert!(Distributor::space(Distributor::MAX_NUM_NODES) <= 10_240);
     }
 }
@@ -0,0 +1,701 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import { Smartolana } from "../target/types/smartolana";
+import { assert } from "chai";
+import {
+  TOKEN_PROGRAM_ID,
+  TOKEN_2022_PROGRAM_ID,
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  ExtensionType,
+  createAccount,
+  createAssociatedTokenAccount,
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  createMint,
+  getAccount,
+  getAssociatedTokenAddressSync,
+  getMintLen,
+  mintTo,
+} from "@solana/spl-token";
+
+describe("token programs", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+
+  const program = anchor.workspace.Smartolana as Program<Smartolana>;
+  const payer = provider.wallet.payer;
+  const user = provider.wallet.publicKey;
+
+  // Initialized by the smartolana suite, which runs first
+  const [blocklistPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("blocklist")],
+    program.programId
+  );
+
+  const [registryPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("validator-registry")],
+    program.programId
+  );
+
+  const pda = (...seeds: Buffer[]) =>
+    anchor.web3.PublicKey.findProgramAddressSync(seeds, program.programId)[0];
+  const blocklistEntryPda = (owner: anchor.web3.PublicKey) =>
+    pda(Buffer.from("blocklist-entry"), owner.toBuffer());
+
+  const i32Bytes = (value: number) => {
+    const buf = Buffer.alloc(4);
+    buf.writeInt32LE(value);
+    return buf;
+  };
+
+  const balance = async (
+    account: anchor.web3.PublicKey,
+    tokenProgram: anchor.web3.PublicKey
+  ) =>
+    Number(
+      (await getAccount(provider.connection, account, undefined, tokenProgram))
+        .amount
+    );
+
+  const fundedKeypair = async () => {
+    const keypair = anchor.web3.Keypair.generate();
+    const sig = await provider.connection.requestAirdrop(
+      keypair.publicKey,
+      1_000_000_000
+    );
+    await provider.connection.confirmTransaction(sig);
+    return keypair;
+  };
+
+  const newMint = (tokenProgram: anchor.web3.PublicKey) =>
+    createMint(
+      provider.connection,
+      payer,
+      user,
+      null,
+      6,
+      anchor.web3.Keypair.generate(),
+      undefined,
+      tokenProgram
+    );
+
+  // Token-2022 mint charging `feeBps` on every transfer
+  const newTransferFeeMint = async (feeBps: number) => {
+    const mintKeypair = anchor.web3.Keypair.generate();
+    const mint = mintKeypair.publicKey;
+    const mintLen = getMintLen([ExtensionType.TransferFeeConfig]);
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(mintLen);
+
+    // Fee capped well above anything transferred here
+    const tx = new anchor.web3.Transaction().add(
+      anchor.web3.SystemProgram.createAccount({
+        fromPubkey: user,
+        newAccountPubkey: mint,
+        space: mintLen,
+        lamports,
+        programId: TOKEN_2022_PROGRAM_ID,
+      }),
+      createInitializeTransferFeeConfigInstruction(
+        mint,
+        user,
+        user,
+        feeBps,
+        BigInt(1_000_000),
+        TOKEN_2022_PROGRAM_ID
+      ),
+      createInitializeMintInstruction(
+        mint,
+        6,
+        user,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      )
+    );
+    await provider.sendAndConfirm(tx, [mintKeypair]);
+    return mint;
+  };
+
+  // ATA of `owner` for `mint`, holding `amount` fresh tokens
+  const fundedAta = async (
+    mint: anchor.web3.PublicKey,
+    owner: anchor.web3.PublicKey,
+    amount: number,
+    tokenProgram: anchor.web3.PublicKey
+  ) => {
+    const ata = await createAssociatedTokenAccount(
+      provider.connection,
+      payer,
+      mint,
+      owner,
+      undefined,
+      tokenProgram
+    );
+    if (amount > 0) {
+      await mintTo(
+        provider.connection,
+        payer,
+        mint,
+        ata,
+        payer,
+        amount,
+        [],
+        undefined,
+        tokenProgram
+      );
+    }
+    return ata;
+  };
+
+  // Opens a CLMM pool over two fresh mints with 1,000,000 of each in range
+  const setupClmm = async (
+    mintA: anchor.web3.PublicKey,
+    mintB: anchor.web3.PublicKey,
+    tokenProgram: anchor.web3.PublicKey
+  ) => {
+    const poolClmm = pda(
+      Buffer.from("pool-clmm"),
+      mintA.toBuffer(),
+      mintB.toBuffer()
+    );
+    const poolSigner = pda(Buffer.from("signer"), poolClmm.toBuffer());
+    const vaultA = anchor.web3.Keypair.generate();
+    const vaultB = anchor.web3.Keypair.generate();
+
+    await program.methods
+      .initPoolClmm(new anchor.BN(1).shln(64), 0, 1, 30)
+      .accountsStrict({
+        payer: user,
+        poolClmm,
+        tokenAMint: mintA,
+        tokenBMint: mintB,
+        vaultA: vaultA.publicKey,
+        vaultB: vaultB.publicKey,
+        poolSigner,
+        tokenProgram,
+        systemProgram: anchor.web3.SystemProgram.programId,
+        rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+      })
+      .signers([vaultA, vaultB])
+      .rpc();
+
+    const [tickLower, tickUpper] = [-10, 10].map((index) =>
+      pda(Buffer.from("tick"), poolClmm.toBuffer(), i32Bytes(index))
+    );
+    for (const [index, tick] of [
+      [-10, tickLower],
+      [10, tickUpper],
+    ] as const) {
+      await program.methods
+        .initTick(index)
+        .accountsStrict({
+          payer: user,
+          poolClmm,
+          tick,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    const userTokenA = await fundedAta(mintA, user, 2_000_000, tokenProgram);
+    const userTokenB = await fundedAta(mintB, user, 2_000_000, tokenProgram);
+
+    // 1,000 units of liquidity per token moved in
+    await program.methods
+      .openPosition(new anchor.BN(1_000_000_000), -10, 10)
+      .accountsStrict({
+        owner: user,
+        poolClmm,
+        userTokenA,
+        userTokenB,
+        vaultA: vaultA.publicKey,
+        vaultB: vaultB.publicKey,
+        poolSigner,
+        tickLower,
+        tickUpper,
+        userPosition: pda(
+          Buffer.from("position"),
+          user.toBuffer(),
+          poolClmm.toBuffer(),
+          i32Bytes(-10),
+          i32Bytes(10)
+        ),
+        tokenAMint: mintA,
+        tokenBMint: mintB,
+        tokenProgram,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    return {
+      poolClmm,
+      poolSigner,
+      vaultA: vaultA.publicKey,
+      vaultB: vaultB.publicKey,
+      userTokenA,
+      userTokenB,
+    };
+  };
+
+  // Creates sender/recipient accounts for `mint` and funds the sender
+  const setupAccounts = async (
+    mint: anchor.web3.PublicKey,
+    tokenProgram: anchor.web3.PublicKey
+  ) => {
+    const from = await createAccount(
+      provider.connection,
+      payer,
+      mint,
+      user,
+      anchor.web3.Keypair.generate(),
+      undefined,
+      tokenProgram
+    );
+    const recipient = anchor.web3.Keypair.generate().publicKey;
+    const to = await createAccount(
+      provider.connection,
+      payer,
+      mint,
+      recipient,
+      undefined,
+      undefined,
+      tokenProgram
+    );
+    await mintTo(
+      provider.connection,
+      payer,
+      mint,
+      from,
+      payer,
+      1_000_000,
+      [],
+      undefined,
+      tokenProgram
+    );
+    return { from, to, recipient };
+  };
+
+  for (const [label, tokenProgram] of [
+    ["SPL Token", TOKEN_PROGRAM_ID],
+    ["Token-2022", TOKEN_2022_PROGRAM_ID],
+  ] as const) {
+    it(`Transfers and burns with ${label}`, async () => {
+      const mint = await createMint(
+        provider.connection,
+        payer,
+        user,
+        null,
+        6,
+        anchor.web3.Keypair.generate(),
+        undefined,
+        tokenProgram
+      );
+      const { from, to, recipient } = await setupAccounts(mint, tokenProgram);
+
+      await program.methods
+        .transferTokens(new anchor.BN(400_000))
+        .accountsStrict({
+          sender: user,
+          from,
+          to,
+          mint,
+          senderBlocklistEntry: blocklistEntryPda(user),
+          recipientBlocklistEntry: blocklistEntryPda(recipient),
+          tokenProgram,
+        })
+        .rpc();
+
+      await program.methods
+        .burnTokens(new anchor.BN(100_000))
+        .accountsStrict({
+          owner: user,
+          ownerAta: from,
+          mint,
+          tokenProgram,
+        })
+        .rpc();
+
+      const fromAccount = await getAccount(
+        provider.connection,
+        from,
+        undefined,
+        tokenProgram
+      );
+      const toAccount = await getAccount(
+        provider.connection,
+        to,
+        undefined,
+        tokenProgram
+      );
+      assert.equal(Number(fromAccount.amount), 500_000);
+      assert.equal(Number(toAccount.amount), 400_000);
+    });
+
+    it(`Stakes and unstakes with ${label}`, async () => {
+      const staker = await fundedKeypair();
+      const profile = pda(Buffer.from("profile"), staker.publicKey.toBuffer());
+      await program.methods
+        .initProfile("staker")
+        .accountsStrict({
+          profile,
+          authority: staker.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([staker])
+        .rpc();
+
+      const stakeMint = await newMint(tokenProgram);
+      const userAta = await fundedAta(
+        stakeMint,
+        staker.publicKey,
+        1_000_000,
+        tokenProgram
+      );
+
+      const poolId = new anchor.BN(Date.now());
+      const pool = pda(
+        Buffer.from("pool"),
+        user.toBuffer(),
+        poolId.toArrayLike(Buffer, "le", 8)
+      );
+      const rewardVaultAuthority = pda(
+        Buffer.from("reward-vault"),
+        pool.toBuffer()
+      );
+      await program.methods
+        .initStakingPool(poolId, label, new anchor.BN(0), new anchor.BN(0))
+        .accountsStrict({
+          pool,
+          authority: user,
+          stakeMint,
+          rewardMint: stakeMint,
+          mintConfig: pda(Buffer.from("mint-config")),
+          rewardVault: getAssociatedTokenAddressSync(
+            stakeMint,
+            rewardVaultAuthority,
+            true,
+            tokenProgram
+          ),
+          rewardVaultAuthority,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          tokenProgram,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+
+      const stakeVault = pda(
+        Buffer.from("stake-vault"),
+        staker.publicKey.toBuffer()
+      );
+      const vaultAta = getAssociatedTokenAddressSync(
+        stakeMint,
+        stakeVault,
+        true,
+        tokenProgram
+      );
+
+      await program.methods
+        .stakeTokens(new anchor.BN(400_000))
+        .accountsStrict({
+          signer: staker.publicKey,
+          user: staker.publicKey,
+          profile,
+          stakeVault,
+          userAta,
+          vaultAta,
+          stakeMint,
+          pool,
+          session: null,
+          userBlocklistEntry: blocklistEntryPda(staker.publicKey),
+          tokenProgram,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          registry: registryPda,
+        })
+        .signers([staker])
+        .rpc();
+
+      assert.equal(await balance(vaultAta, tokenProgram), 400_000);
+      const staked = await program.account.stakeVault.fetch(stakeVault);
+      assert.equal(staked.amount.toNumber(), 400_000);
+
+      // No lock period, so it comes straight back
+      await program.methods
+        .unstakeTokens()
+        .accountsStrict({
+          user: staker.publicKey,
+          profile,
+          stakeVault,
+          userAta,
+          vaultAta,
+          stakeMint,
+          pool,
+          userBlocklistEntry: blocklistEntryPda(staker.publicKey),
+          tokenProgram,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+          registry: registryPda,
+        })
+        .signers([staker])
+        .rpc();
+
+      assert.equal(await balance(userAta, tokenProgram), 1_000_000);
+      assert.equal(await balance(vaultAta, tokenProgram), 0);
+    });
+
+    it(`Fills an escrow with ${label}`, async () => {
+      const initializer = await fundedKeypair();
+      const taker = await fundedKeypair();
+      const mintOffered = await newMint(tokenProgram);
+      const mintExpected = await newMint(tokenProgram);
+
+      const deposit = await fundedAta(
+        mintOffered,
+        initializer.publicKey,
+        1_000_000,
+        tokenProgram
+      );
+      const initializerReceive = await fundedAta(
+        mintExpected,
+        initializer.publicKey,
+        0,
+        tokenProgram
+      );
+      const takerPayment = await fundedAta(
+        mintExpected,
+        taker.publicKey,
+        1_000_000,
+        tokenProgram
+      );
+      const takerReceive = await fundedAta(
+        mintOffered,
+        taker.publicKey,
+        0,
+        tokenProgram
+      );
+
+      const escrow = pda(Buffer.from("escrow"), initializer.publicKey.toBuffer());
+      const vaultAuthority = pda(
+        Buffer.from("vault-authority"),
+        initializer.publicKey.toBuffer()
+      );
+      const vaultAmount = getAssociatedTokenAddressSync(
+        mintOffered,
+        vaultAuthority,
+        true,
+        tokenProgram
+      );
+
+      const now = Math.floor(Date.now() / 1000);
+      await program.methods
+        .initEscrow(
+          new anchor.BN(300_000),
+          new anchor.BN(500_000),
+          new anchor.BN(now + 2)
+        )
+        .accountsStrict({
+          initializer: initializer.publicKey,
+          initializerDepositTokenAccount: deposit,
+          mintOffered,
+          mintExpected,
+          escrow,
+          vaultAmount,
+          vaultAuthority,
+          tokenProgram,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          rent: anchor.web3.SYSVAR_RENT_PUBKEY,
+        })
+        .signers([initializer])
+        .rpc();
+
+      await new Promise((r) => setTimeout(r, 4000));
+
+      await program.methods
+        .fulfillEscrow()
+        .accountsStrict({
+          taker: taker.publicKey,
+          takerPaymentAta: takerPayment,
+          initializerReceiveAta: initializerReceive,
+          vaultAmount,
+          vaultAuthority,
+          takerReceiveAta: takerReceive,
+          escrow,
+          mintOffered,
+          mintExpected,
+          tokenProgram,
+        })
+        .signers([taker])
+        .rpc();
+
+      assert.equal(await balance(takerReceive, tokenProgram), 300_000);
+      assert.equal(await balance(initializerReceive, tokenProgram), 500_000);
+    });
+
+    it(`Swaps through a CLMM pool with ${label}`, async () => {
+      const mintA = await newMint(tokenProgram);
+      const mintB = await newMint(tokenProgram);
+      const clmm = await setupClmm(mintA, mintB, tokenProgram);
+
+      // 0.3% pool fee on 100,000 in
+      await program.methods
+        .swapClmm(new anchor.BN(100_000), new anchor.BN(99_700), true)
+        .accountsStrict({
+          user,
+          poolClmm: clmm.poolClmm,
+          userSourceToken: clmm.userTokenA,
+          userDestinationToken: clmm.userTokenB,
+          vaultSource: clmm.vaultA,
+          vaultDestination: clmm.vaultB,
+          poolSigner: clmm.poolSigner,
+          sourceMint: mintA,
+          destinationMint: mintB,
+          tokenProgram,
+        })
+        .rpc();
+
+      assert.equal(await balance(clmm.userTokenA, tokenProgram), 900_000);
+      assert.equal(await balance(clmm.userTokenB, tokenProgram), 1_099_700);
+      assert.equal(await balance(clmm.vaultA, tokenProgram), 1_100_000);
+    });
+  }
+
+  it("Batch transfers a Token-2022 mint through the hook-aware CPI", async () => {
+    const mint = await newMint(TOKEN_2022_PROGRAM_ID);
+    const from = await fundedAta(mint, user, 1_000_000, TOKEN_2022_PROGRAM_ID);
+    const holder = anchor.web3.Keypair.generate().publicKey;
+    const ata = getAssociatedTokenAddressSync(
+      mint,
+      holder,
+      false,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    await program.methods
+      .batchTransfer([new anchor.BN(250_000)], true)
+      .accountsStrict({
+        sender: user,
+        from,
+        mint,
+        senderBlocklistEntry: blocklistEntryPda(user),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .remainingAccounts([
+        { pubkey: holder, isSigner: false, isWritable: false },
+        { pubkey: ata, isSigner: false, isWritable: true },
+        {
+          pubkey: blocklistEntryPda(holder),
+          isSigner: false,
+          isWritable: false,
+        },
+      ])
+      .rpc();
+
+    assert.equal(await balance(ata, TOKEN_2022_PROGRAM_ID), 250_000);
+  });
+
+  it("Applies the blocklist to the global mint only", async () => {
+    const mint = await newMint(TOKEN_2022_PROGRAM_ID);
+    const { from, to, recipient } = await setupAccounts(
+      mint,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const entry = blocklistEntryPda(recipient);
+
+    await program.methods
+      .addToBlocklist(recipient)
+      .accountsStrict({
+        blocklist: blocklistPda,
+        entry,
+        admin: user,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .transferTokens(new anchor.BN(100_000))
+      .accountsStrict({
+        sender: user,
+        from,
+        to,
+        mint,
+        senderBlocklistEntry: blocklistEntryPda(user),
+        recipientBlocklistEntry: entry,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .rpc();
+    assert.equal(await balance(to, TOKEN_2022_PROGRAM_ID), 100_000);
+
+    await program.methods
+      .removeFromBlocklist(recipient)
+      .accountsStrict({ blocklist: blocklistPda, entry, admin: user })
+      .rpc();
+  });
+
+  it("Withholds the fee on a Token-2022 transfer-fee mint", async () => {
+    const mint = await newTransferFeeMint(100);
+    const { from, to, recipient } = await setupAccounts(
+      mint,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    await program.methods
+      .transferTokens(new anchor.BN(100_000))
+      .accountsStrict({
+        sender: user,
+        from,
+        to,
+        mint,
+        senderBlocklistEntry: blocklistEntryPda(user),
+        recipientBlocklistEntry: blocklistEntryPda(recipient),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .rpc();
+
+    const toAccount = await getAccount(
+      provider.connection,
+      to,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    assert.equal(Number(toAccount.amount), 99_000);
+  });
+
+  it("Prices a CLMM swap on what reached the vault after the transfer fee", async () => {
+    const mintA = await newTransferFeeMint(100);
+    const mintB = await newMint(TOKEN_2022_PROGRAM_ID);
+    const clmm = await setupClmm(mintA, mintB, TOKEN_2022_PROGRAM_ID);
+    const vaultBefore = await balance(clmm.vaultA, TOKEN_2022_PROGRAM_ID);
+
+    // 1% transfer fee leaves 99,000 in the vault, then 0.3% pool fee
+    await program.methods
+      .swapClmm(new anchor.BN(100_000), new anchor.BN(98_703), true)
+      .accountsStrict({
+        user,
+        poolClmm: clmm.poolClmm,
+        userSourceToken: clmm.userTokenA,
+        userDestinationToken: clmm.userTokenB,
+        vaultSource: clmm.vaultA,
+        vaultDestination: clmm.vaultB,
+        poolSigner: clmm.poolSigner,
+        sourceMint: mintA,
+        destinationMint: mintB,
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+      })
+      .rpc();
+
+    assert.equal(
+      (await balance(clmm.vaultA, TOKEN_2022_PROGRAM_ID)) - vaultBefore,
+      99_000
+    );
+    assert.equal(
+      await balance(clmm.userTokenB, TOKEN_2022_PROGRAM_ID),
+      1_098_703
+    );
+  });
+});